@@ -0,0 +1,167 @@
+use crate::{ButtonEvent, ButtonRole, Micros, RandomSource};
+
+/// How the reaction times of a bot are spread
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ReactionDistribution {
+    /// Anywhere in `min..=max`, all equally likely
+    Uniform { min: Micros, max: Micros },
+    /// Bell curve around `mean`, cut at 6 standard deviations
+    Normal { mean: Micros, std_dev: Micros },
+}
+
+// Resolution of each uniform draw summed into a normal one
+const UNIT: u64 = 1 << 16;
+
+impl ReactionDistribution {
+    /// A casual player, around 400 ms
+    pub const EASY: Self = Self::Normal {
+        mean: 400_000,
+        std_dev: 60_000,
+    };
+    /// A sharp player, around 200 ms
+    pub const HARD: Self = Self::Normal {
+        mean: 200_000,
+        std_dev: 25_000,
+    };
+
+    /// Slowest reaction it can draw
+    pub const fn slowest(&self) -> Micros {
+        match *self {
+            Self::Uniform { max, .. } => max,
+            Self::Normal { mean, std_dev } => mean.saturating_add(std_dev.saturating_mul(6)),
+        }
+    }
+
+    /// Uniform ones don't end before they start
+    pub const fn is_valid(&self) -> bool {
+        match *self {
+            Self::Uniform { min, max } => min <= max,
+            Self::Normal { .. } => true,
+        }
+    }
+
+    pub fn sample(&self, rng: &mut impl RandomSource) -> Micros {
+        match *self {
+            Self::Uniform { min, max } => rng.range_inclusive(min, max),
+            // Irwin-Hall: 12 uniforms over [0, 1) add up to a mean of 6 and a deviation of 1
+            Self::Normal { mean, std_dev } => {
+                let sum: u64 = (0..12).map(|_| rng.next_u64() % UNIT).sum();
+                let offset = sum.abs_diff(6 * UNIT) * std_dev / UNIT;
+                match sum >= 6 * UNIT {
+                    true => mean + offset,
+                    false => mean.saturating_sub(offset),
+                }
+            }
+        }
+    }
+}
+
+/// A virtual player, answers each GO with a press like a button would
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Bot {
+    player: ButtonRole,
+    reaction: ReactionDistribution,
+    /// How long each press is held
+    hold: Micros,
+}
+
+impl Bot {
+    pub const fn new(player: ButtonRole, reaction: ReactionDistribution) -> Self {
+        Self {
+            player,
+            reaction,
+            hold: 150_000,
+        }
+    }
+
+    pub fn player(&self) -> ButtonRole {
+        self.player
+    }
+
+    /// Press then release answering a GO at `go_at`, to publish when they're due
+    pub fn on_go(&self, go_at: Micros, rng: &mut impl RandomSource) -> [ButtonEvent; 2] {
+        let pressed_at = go_at + self.reaction.sample(rng);
+        [
+            ButtonEvent::Pressed {
+                player: self.player,
+                at: pressed_at,
+            },
+            ButtonEvent::Released {
+                player: self.player,
+                at: pressed_at + self.hold,
+                held: self.hold,
+            },
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Steps through the whole u64 range, spreading draws evenly
+    struct Sweep(u64);
+
+    impl RandomSource for Sweep {
+        fn next_u64(&mut self) -> u64 {
+            self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            self.0
+        }
+    }
+
+    struct Fixed(u64);
+
+    impl RandomSource for Fixed {
+        fn next_u64(&mut self) -> u64 {
+            self.0
+        }
+    }
+
+    #[test]
+    fn uniform_stays_in_range() {
+        let uniform = ReactionDistribution::Uniform {
+            min: 150_000,
+            max: 250_000,
+        };
+        let mut rng = Sweep(0);
+        assert!((0..1_000)
+            .map(|_| uniform.sample(&mut rng))
+            .all(|t| (150_000..=250_000).contains(&t)));
+        let point = ReactionDistribution::Uniform { min: 5, max: 5 };
+        assert_eq!(point.sample(&mut rng), 5);
+    }
+
+    #[test]
+    fn normal_centers_on_the_mean() {
+        let mut rng = Sweep(1);
+        let samples = 1_200;
+        let total: Micros = (0..samples)
+            .map(|_| ReactionDistribution::HARD.sample(&mut rng))
+            .sum();
+        assert!((total / samples).abs_diff(200_000) < 5_000);
+
+        // Middle draws give the mean, extreme draws stop at 6 standard deviations
+        assert_eq!(
+            ReactionDistribution::EASY.sample(&mut Fixed(UNIT / 2)),
+            400_000
+        );
+        let slowest = ReactionDistribution::EASY.sample(&mut Fixed(UNIT - 1));
+        assert!(slowest < 400_000 + 6 * 60_000);
+    }
+
+    #[test]
+    fn bot_presses_after_go() {
+        let bot = Bot::new(ButtonRole::PLAYER_2, ReactionDistribution::HARD);
+        let [press, release] = bot.on_go(1_000_000, &mut Fixed(UNIT / 2));
+        assert_eq!(
+            press,
+            ButtonEvent::Pressed {
+                player: ButtonRole::PLAYER_2,
+                at: 1_200_000
+            }
+        );
+        assert_eq!(release.at(), 1_350_000);
+    }
+}
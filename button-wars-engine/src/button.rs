@@ -0,0 +1,275 @@
+use heapless::Vec;
+
+use crate::{ButtonRole, Micros};
+
+/// What a button did, classified from its debounced edges
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ButtonEvent {
+    /// Press edge
+    Pressed { player: ButtonRole, at: Micros },
+    /// Release edge, after holding the button for `held`
+    Released {
+        player: ButtonRole,
+        at: Micros,
+        held: Micros,
+    },
+    /// Still held [`ClickConfig::long_press`] after the press
+    LongPress { player: ButtonRole, at: Micros },
+    /// Still held after a long press, once per [`ClickConfig::repeat_interval`]
+    Repeat {
+        player: ButtonRole,
+        at: Micros,
+        count: u32,
+    },
+    /// Second click pressed within [`ClickConfig::double_click`] of the first release
+    DoubleClick { player: ButtonRole, at: Micros },
+}
+
+impl ButtonEvent {
+    pub fn player(&self) -> ButtonRole {
+        match *self {
+            ButtonEvent::Pressed { player, .. }
+            | ButtonEvent::Released { player, .. }
+            | ButtonEvent::LongPress { player, .. }
+            | ButtonEvent::Repeat { player, .. }
+            | ButtonEvent::DoubleClick { player, .. } => player,
+        }
+    }
+
+    pub fn at(&self) -> Micros {
+        match *self {
+            ButtonEvent::Pressed { at, .. }
+            | ButtonEvent::Released { at, .. }
+            | ButtonEvent::LongPress { at, .. }
+            | ButtonEvent::Repeat { at, .. }
+            | ButtonEvent::DoubleClick { at, .. } => at,
+        }
+    }
+}
+
+/// Timings used to classify presses
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ClickConfig {
+    pub long_press: Micros,
+    pub repeat_interval: Micros,
+    /// Longest gap from a release to the next press for a double click
+    pub double_click: Micros,
+}
+
+impl Default for ClickConfig {
+    fn default() -> Self {
+        Self {
+            long_press: 800_000,
+            repeat_interval: 200_000,
+            double_click: 300_000,
+        }
+    }
+}
+
+/// Events out of a single edge, the press edge can also complete a double click
+pub type ButtonEvents = Vec<ButtonEvent, 2>;
+
+/// Turns the debounced edges of one button into [`ButtonEvent`]s
+///
+/// Time based events are emitted by [`Self::poll`], to be called at [`Self::deadline`].
+#[derive(Clone, Debug)]
+pub struct ButtonDecoder {
+    player: ButtonRole,
+    config: ClickConfig,
+    pressed_at: Option<Micros>,
+    repeats: u32,
+    /// Release of the last short click, a double click can follow it
+    click_released_at: Option<Micros>,
+    /// Current press completed a double click, so can't start another one
+    second_click: bool,
+}
+
+impl ButtonDecoder {
+    pub fn new(player: ButtonRole, config: ClickConfig) -> Self {
+        Self {
+            player,
+            config,
+            pressed_at: None,
+            repeats: 0,
+            click_released_at: None,
+            second_click: false,
+        }
+    }
+
+    pub fn player(&self) -> ButtonRole {
+        self.player
+    }
+
+    pub fn is_pressed(&self) -> bool {
+        self.pressed_at.is_some()
+    }
+
+    /// Press edge, a press while already pressed is ignored
+    pub fn on_press(&mut self, at: Micros) -> ButtonEvents {
+        let mut events = ButtonEvents::new();
+        if self.pressed_at.is_some() {
+            return events;
+        }
+        self.pressed_at = Some(at);
+        self.repeats = 0;
+        let player = self.player;
+        let _ = events.push(ButtonEvent::Pressed { player, at });
+        self.second_click = self
+            .click_released_at
+            .take()
+            .is_some_and(|released_at| at.saturating_sub(released_at) <= self.config.double_click);
+        if self.second_click {
+            let _ = events.push(ButtonEvent::DoubleClick { player, at });
+        }
+        events
+    }
+
+    /// Release edge, a release without a press is ignored
+    pub fn on_release(&mut self, at: Micros) -> Option<ButtonEvent> {
+        let pressed_at = self.pressed_at.take()?;
+        let held = at.saturating_sub(pressed_at);
+        // Only a short click can start a double click, and not the second click of one
+        let starts_double_click = held < self.config.long_press && !self.second_click;
+        self.click_released_at = starts_double_click.then_some(at);
+        Some(ButtonEvent::Released {
+            player: self.player,
+            at,
+            held,
+        })
+    }
+
+    /// When the next long press or repeat is due, `None` while released
+    pub fn deadline(&self) -> Option<Micros> {
+        let pressed_at = self.pressed_at?;
+        let repeats = self.repeats as Micros * self.config.repeat_interval;
+        Some(pressed_at + self.config.long_press + repeats)
+    }
+
+    /// Long press or repeat due at `now`, at most one per call
+    pub fn poll(&mut self, now: Micros) -> Option<ButtonEvent> {
+        let due = self.deadline().filter(|&due| now >= due)?;
+        let player = self.player;
+        let event = match self.repeats {
+            0 => ButtonEvent::LongPress { player, at: due },
+            count => ButtonEvent::Repeat {
+                player,
+                at: due,
+                count,
+            },
+        };
+        self.repeats += 1;
+        Some(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const P1: ButtonRole = ButtonRole::PLAYER_1;
+
+    fn decoder() -> ButtonDecoder {
+        ButtonDecoder::new(P1, ClickConfig::default())
+    }
+
+    #[test]
+    fn short_click_reports_hold_duration() {
+        let mut button = decoder();
+        assert_eq!(
+            button.on_press(1_000),
+            [ButtonEvent::Pressed {
+                player: P1,
+                at: 1_000
+            }]
+        );
+        assert!(button.is_pressed());
+        assert_eq!(
+            button.on_release(121_000),
+            Some(ButtonEvent::Released {
+                player: P1,
+                at: 121_000,
+                held: 120_000
+            })
+        );
+        assert_eq!(button.deadline(), None);
+        assert_eq!(button.poll(10_000_000), None);
+    }
+
+    #[test]
+    fn long_press_then_repeats_while_held() {
+        let mut button = decoder();
+        button.on_press(0);
+        assert_eq!(button.deadline(), Some(800_000));
+        assert_eq!(button.poll(799_999), None);
+        assert_eq!(
+            button.poll(800_000),
+            Some(ButtonEvent::LongPress {
+                player: P1,
+                at: 800_000
+            })
+        );
+        assert_eq!(button.deadline(), Some(1_000_000));
+        assert_eq!(
+            button.poll(1_000_100),
+            Some(ButtonEvent::Repeat {
+                player: P1,
+                at: 1_000_000,
+                count: 1
+            })
+        );
+        assert_eq!(button.poll(1_200_000).map(|e| e.at()), Some(1_200_000));
+        assert!(matches!(
+            button.on_release(1_250_000),
+            Some(ButtonEvent::Released {
+                held: 1_250_000,
+                ..
+            })
+        ));
+        assert_eq!(button.deadline(), None);
+    }
+
+    #[test]
+    fn second_press_soon_after_a_click_is_a_double_click() {
+        let mut button = decoder();
+        button.on_press(0);
+        button.on_release(100_000);
+        let events = button.on_press(350_000);
+        assert_eq!(
+            events,
+            [
+                ButtonEvent::Pressed {
+                    player: P1,
+                    at: 350_000
+                },
+                ButtonEvent::DoubleClick {
+                    player: P1,
+                    at: 350_000
+                }
+            ]
+        );
+        button.on_release(400_000);
+        // A third click doesn't chain into another double click
+        assert_eq!(button.on_press(500_000).len(), 1);
+    }
+
+    #[test]
+    fn no_double_click_when_too_slow_or_after_a_long_press() {
+        let mut button = decoder();
+        button.on_press(0);
+        button.on_release(100_000);
+        assert_eq!(button.on_press(400_001).len(), 1);
+        button.on_release(1_300_000);
+        assert_eq!(button.on_press(1_400_000).len(), 1);
+    }
+
+    #[test]
+    fn stray_edges_are_ignored() {
+        let mut button = decoder();
+        assert_eq!(button.on_release(10), None);
+        button.on_press(20);
+        assert!(button.on_press(30).is_empty());
+        assert_eq!(button.on_release(40).map(|e| e.player()), Some(P1));
+    }
+}
@@ -0,0 +1,76 @@
+use crate::{ButtonRole, Micros, MAX_PLAYERS};
+
+/// Per-button debounce measured by the boot calibration, as kept in the [`Store`](crate::Store)
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DebounceCalibration {
+    /// Indexed by [`ButtonRole::index`], 0 for a button never measured
+    pub debounce_ms: [u16; MAX_PLAYERS],
+}
+
+impl DebounceCalibration {
+    /// Bytes taken by a record: magic and every debounce
+    pub const SIZE: usize = 4 + 2 * MAX_PLAYERS;
+    // Bumped from "BWDB" when records went from 2 to 8 buttons
+    const MAGIC: [u8; 4] = *b"BWD8";
+
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut bytes = [0; Self::SIZE];
+        bytes[..4].copy_from_slice(&Self::MAGIC);
+        for (chunk, ms) in bytes[4..].chunks_exact_mut(2).zip(self.debounce_ms) {
+            chunk.copy_from_slice(&ms.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// `None` for another layout
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let bytes = bytes.get(..Self::SIZE)?;
+        if bytes[..4] != Self::MAGIC {
+            return None;
+        }
+        let mut debounce_ms = [0; MAX_PLAYERS];
+        for (ms, chunk) in debounce_ms.iter_mut().zip(bytes[4..].chunks_exact(2)) {
+            *ms = u16::from_le_bytes([chunk[0], chunk[1]]);
+        }
+        Some(Self { debounce_ms })
+    }
+
+    /// Bounce time measured on the button, `None` if it never was
+    pub fn debounce(&self, role: ButtonRole) -> Option<Micros> {
+        match self.debounce_ms[role.index()] {
+            0 => None,
+            ms => Some(ms as Micros * 1_000),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CALIBRATION: DebounceCalibration = DebounceCalibration {
+        debounce_ms: [55, 72, 0, 0, 0, 0, 0, 0],
+    };
+
+    #[test]
+    fn round_trips_through_bytes() {
+        let bytes = CALIBRATION.to_bytes();
+        assert_eq!(DebounceCalibration::from_bytes(&bytes), Some(CALIBRATION));
+        assert_eq!(CALIBRATION.debounce(ButtonRole::PLAYER_2), Some(72_000));
+        assert_eq!(CALIBRATION.debounce(ButtonRole::ALL[2]), None);
+    }
+
+    #[test]
+    fn rejects_other_layouts() {
+        assert_eq!(DebounceCalibration::from_bytes(&[0xFF; 64]), None);
+        assert_eq!(DebounceCalibration::from_bytes(&[0; 4]), None);
+        let mut bytes = CALIBRATION.to_bytes();
+        bytes[..4].copy_from_slice(b"BWR1");
+        assert_eq!(DebounceCalibration::from_bytes(&bytes), None);
+        assert_eq!(
+            DebounceCalibration::from_bytes(&CALIBRATION.to_bytes()[..10]),
+            None
+        );
+    }
+}
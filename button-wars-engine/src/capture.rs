@@ -0,0 +1,85 @@
+use crate::Micros;
+
+/// Raw level change timestamped by the PIO edge capture, still bouncy
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct CapturedEdge {
+    pub pressed: bool,
+    pub at: Micros,
+}
+
+// Bits of the tick counter in a word, the MSB holds the level
+const COUNT_BITS: u32 = 31;
+const COUNT_MASK: u32 = (1 << COUNT_BITS) - 1;
+
+/// Turns the words pushed by the edge capture program into [`CapturedEdge`]s
+///
+/// A word is `pressed << 31 | x`, `x` being a counter started at 0 and
+/// decremented once per microsecond. Only 31 bits of it make it into the
+/// word, so it wraps every 35 minutes; the read time resolves the wrap.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct EdgeCaptureDecoder {
+    /// When the program started counting
+    origin: Micros,
+}
+
+impl EdgeCaptureDecoder {
+    pub const fn new(origin: Micros) -> Self {
+        Self { origin }
+    }
+
+    /// Decode a word read at `now`, the edge is the latest one matching its count
+    pub fn decode(&self, word: u32, now: Micros) -> CapturedEdge {
+        let ticks = (word & COUNT_MASK).wrapping_neg() & COUNT_MASK;
+        let elapsed = now.saturating_sub(self.origin);
+        let behind = (elapsed as u32).wrapping_sub(ticks) & COUNT_MASK;
+        CapturedEdge {
+            pressed: word >> COUNT_BITS == 1,
+            at: self.origin + elapsed.saturating_sub(behind as Micros),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Word pushed after `ticks` decrements of the counter
+    fn word(pressed: bool, ticks: u64) -> u32 {
+        let x = 0u32.wrapping_sub(ticks as u32);
+        (pressed as u32) << COUNT_BITS | x & COUNT_MASK
+    }
+
+    #[test]
+    fn timestamps_count_from_the_origin() {
+        let decoder = EdgeCaptureDecoder::new(5_000);
+        assert_eq!(
+            decoder.decode(word(true, 1_234), 7_000),
+            CapturedEdge {
+                pressed: true,
+                at: 6_234
+            }
+        );
+        assert!(!decoder.decode(word(false, 1), 6_000).pressed);
+    }
+
+    #[test]
+    fn late_reads_and_wraps_resolve_to_the_latest_match() {
+        let decoder = EdgeCaptureDecoder::new(0);
+        let wrap = 1 << COUNT_BITS;
+        // Read long after it happened
+        assert_eq!(decoder.decode(word(true, 10), 1_000_000).at, 10);
+        // Counter wrapped a few times since the origin
+        let at = 3 * wrap + 42;
+        assert_eq!(decoder.decode(word(true, at), at + 500).at, at);
+        assert_eq!(decoder.decode(word(true, wrap - 1), wrap + 5).at, wrap - 1);
+    }
+
+    #[test]
+    fn edge_read_before_its_count_is_clamped_to_the_origin() {
+        let decoder = EdgeCaptureDecoder::new(1_000);
+        // Counts from the future can only be a previous wrap, never before the origin
+        assert_eq!(decoder.decode(word(true, 50), 1_010).at, 1_000);
+    }
+}
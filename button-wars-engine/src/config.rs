@@ -0,0 +1,937 @@
+use crate::{
+    as_millis, ButtonRole, DebounceKind, Foreperiod, MatchFormat, Micros, ReactionDistribution,
+    MAX_PLAYERS,
+};
+
+/// GPIO numbers of the button and LED of a player
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PlayerPins {
+    pub button: u8,
+    pub led: u8,
+}
+
+/// Pace of the LED feedback, in ms
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct LedTimings {
+    /// Each on and off of the countdown before a round
+    pub countdown_ms: u16,
+    /// Each on and off of the winner's score after a round
+    pub score_blink_ms: u16,
+    /// Each on and off of the offender's strobe on a false start
+    pub false_start_strobe_ms: u16,
+}
+
+/// Settings of the board, as kept in the [`Store`](crate::Store)
+///
+/// Fields are only ever appended: a record from an older version lacks the last ones, they
+/// take their default, and one from a newer version has extra ones, they're skipped.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Config {
+    /// Indexed by [`ButtonRole::index`](crate::ButtonRole::index), the first players in use
+    pub pins: [PlayerPins; MAX_PLAYERS],
+    /// Debounce time of the buttons the calibration never measured
+    pub debounce_ms: u16,
+    /// Without feeding for this long the chip reboots
+    pub watchdog_starve_ms: u32,
+    pub watchdog_feed_ms: u32,
+    /// Format picked at boot
+    pub format: MatchFormat,
+    pub leds: LedTimings,
+    /// LED hold before GO of match rounds, for formats without their own
+    pub foreperiod: Foreperiod,
+    /// LED hold before GO of practice trials
+    pub practice_foreperiod: Foreperiod,
+    /// Virtual player taking the seat after the buttons, `None` for buttons only
+    pub bot: Option<ReactionDistribution>,
+    /// Debouncer of each button over its debounce time, indexed like `pins`
+    pub debouncers: [DebounceKind; MAX_PLAYERS],
+}
+
+// Magic, version, payload length
+const HEADER: usize = 8;
+// Payload of the current version: pins, debounce, watchdog, format, LED timings, foreperiods,
+// bot and debouncers
+const PAYLOAD: usize = 2 * MAX_PLAYERS + 2 + 8 + 2 + 6 + 2 * FOREPERIOD + BOT + MAX_PLAYERS;
+// Kind then three words
+const FOREPERIOD: usize = 1 + 3 * 4;
+// Kind then two words
+const BOT: usize = 1 + 2 * 4;
+
+impl Config {
+    /// Bumped whenever fields are appended
+    pub const VERSION: u16 = 1;
+    /// Bytes taken by a record of the current version: header and payload
+    pub const SIZE: usize = HEADER + PAYLOAD;
+    const MAGIC: [u8; 4] = *b"BWCF";
+
+    /// The reference board, as it was before the config existed
+    pub const DEFAULT: Self = Self {
+        pins: [
+            PlayerPins { button: 10, led: 5 },
+            PlayerPins { button: 11, led: 8 },
+            PlayerPins { button: 12, led: 6 },
+            PlayerPins { button: 13, led: 7 },
+            PlayerPins { button: 14, led: 9 },
+            PlayerPins {
+                button: 15,
+                led: 16,
+            },
+            PlayerPins {
+                button: 17,
+                led: 18,
+            },
+            PlayerPins {
+                button: 19,
+                led: 20,
+            },
+        ],
+        debounce_ms: 50,
+        watchdog_starve_ms: 3_000,
+        watchdog_feed_ms: 500,
+        format: MatchFormat::PRESETS[0],
+        leds: LedTimings {
+            countdown_ms: 750,
+            score_blink_ms: 500,
+            false_start_strobe_ms: 40,
+        },
+        foreperiod: Foreperiod::NON_AGING,
+        practice_foreperiod: Foreperiod::NON_AGING,
+        bot: None,
+        debouncers: [DebounceKind::SleepVerify; MAX_PLAYERS],
+    };
+
+    /// Longest starve time the RP2040 watchdog counter holds, past it arming the watchdog panics
+    pub const MAX_WATCHDOG_STARVE_MS: u32 = 0xFF_FFFF / 2 / 1_000;
+
+    /// Longest debounce or LED timing. With the holds capped too, a round goes on well within
+    /// the firmware's liveness timeout between two heartbeats
+    pub const MAX_TIMING_MS: u16 = 1_000;
+
+    /// Longest hold before GO a foreperiod may draw, the built-in ones of the formats included
+    pub const MAX_HOLD: Micros = 10_000_000;
+
+    /// Slowest reaction a bot may draw, well past the time any round gives to press
+    pub const MAX_BOT_REACTION: Micros = 10_000_000;
+
+    /// At least one of the `players` with a button, the bot making 2 to [`MAX_PLAYERS`]
+    /// seats with them, pins of those players are GPIO 0 to 29, none used twice nor in
+    /// `reserved`, the watchdog is fed well before it starves and can be armed, the timings,
+    /// holds and bot reactions are within their caps, the foreperiods and the bot can be
+    /// drawn from and the format plays at least a round
+    pub const fn is_valid(&self, players: usize, reserved: &[u8]) -> bool {
+        let seats = players + self.bot.is_some() as usize;
+        if players == 0
+            || seats < 2
+            || seats > MAX_PLAYERS
+            || self.watchdog_feed_ms == 0
+            || self.watchdog_feed_ms.saturating_mul(2) > self.watchdog_starve_ms
+            || self.watchdog_starve_ms > Self::MAX_WATCHDOG_STARVE_MS
+            || self.debounce_ms > Self::MAX_TIMING_MS
+            || self.leds.countdown_ms > Self::MAX_TIMING_MS
+            || self.leds.score_blink_ms > Self::MAX_TIMING_MS
+            || self.leds.false_start_strobe_ms > Self::MAX_TIMING_MS
+            || matches!(
+                self.format,
+                MatchFormat::BestOf(0)
+                    | MatchFormat::FirstTo(0)
+                    | MatchFormat::WinByTwo(0)
+                    | MatchFormat::TotalTime(0)
+            )
+            || matches!(self.foreperiod, Foreperiod::Fixed(_))
+            || matches!(self.practice_foreperiod, Foreperiod::Fixed(_))
+            || !self.foreperiod.is_valid()
+            || !self.practice_foreperiod.is_valid()
+            || self.foreperiod.longest() > Self::MAX_HOLD
+            || self.practice_foreperiod.longest() > Self::MAX_HOLD
+        {
+            return false;
+        }
+        if let Some(bot) = self.bot {
+            if !bot.is_valid() || bot.slowest() > Self::MAX_BOT_REACTION {
+                return false;
+            }
+        }
+        let mut pins = [0u8; 2 * MAX_PLAYERS];
+        let mut i = 0;
+        while i < players {
+            pins[2 * i] = self.pins[i].button;
+            pins[2 * i + 1] = self.pins[i].led;
+            i += 1;
+        }
+        let mut i = 0;
+        while i < 2 * players {
+            if pins[i] >= 30 {
+                return false;
+            }
+            let mut j = 0;
+            while j < reserved.len() {
+                if pins[i] == reserved[j] {
+                    return false;
+                }
+                j += 1;
+            }
+            let mut j = i + 1;
+            while j < 2 * players {
+                if pins[i] == pins[j] {
+                    return false;
+                }
+                j += 1;
+            }
+            i += 1;
+        }
+        true
+    }
+
+    /// A fixed foreperiod set has no room in the record, it reads back as the default
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut bytes = [0; Self::SIZE];
+        bytes[..4].copy_from_slice(&Self::MAGIC);
+        bytes[4..6].copy_from_slice(&Self::VERSION.to_le_bytes());
+        bytes[6..8].copy_from_slice(&(PAYLOAD as u16).to_le_bytes());
+
+        let mut out = Writer {
+            bytes: &mut bytes[HEADER..],
+            at: 0,
+        };
+        for pins in self.pins {
+            out.u8(pins.button);
+            out.u8(pins.led);
+        }
+        out.u16(self.debounce_ms);
+        out.u32(self.watchdog_starve_ms);
+        out.u32(self.watchdog_feed_ms);
+        let (kind, count) = match self.format {
+            MatchFormat::BestOf(n) => (0, n),
+            MatchFormat::FirstTo(n) => (1, n),
+            MatchFormat::WinByTwo(n) => (2, n),
+            MatchFormat::TotalTime(n) => (3, n),
+            MatchFormat::SuddenDeath => (4, 0),
+        };
+        out.u8(kind);
+        out.u8(count);
+        out.u16(self.leds.countdown_ms);
+        out.u16(self.leds.score_blink_ms);
+        out.u16(self.leds.false_start_strobe_ms);
+        out.foreperiod(self.foreperiod);
+        out.foreperiod(self.practice_foreperiod);
+        out.bot(self.bot);
+        for kind in self.debouncers {
+            out.u8(kind as u8);
+        }
+        bytes
+    }
+
+    /// Config and the version it was saved with, `None` for another record or a cut one
+    ///
+    /// Fields missing from an older version keep their [`Self::DEFAULT`].
+    pub fn from_bytes(bytes: &[u8]) -> Option<(Self, u16)> {
+        let header = bytes.get(..HEADER)?;
+        if header[..4] != Self::MAGIC {
+            return None;
+        }
+        let version = u16::from_le_bytes([header[4], header[5]]);
+        let len = u16::from_le_bytes([header[6], header[7]]) as usize;
+        let mut config = Self::DEFAULT;
+        let mut input = Reader {
+            bytes: bytes.get(HEADER..HEADER + len)?,
+        };
+        // Stops at the first field the record doesn't have
+        let _ = config.read(&mut input);
+        Some((config, version))
+    }
+
+    fn read(&mut self, input: &mut Reader) -> Option<()> {
+        for pins in self.pins.iter_mut() {
+            *pins = PlayerPins {
+                button: input.u8()?,
+                led: input.u8()?,
+            };
+        }
+        self.debounce_ms = input.u16()?;
+        self.watchdog_starve_ms = input.u32()?;
+        self.watchdog_feed_ms = input.u32()?;
+        let (kind, count) = (input.u8()?, input.u8()?);
+        self.format = match kind {
+            0 => MatchFormat::BestOf(count),
+            1 => MatchFormat::FirstTo(count),
+            2 => MatchFormat::WinByTwo(count),
+            3 => MatchFormat::TotalTime(count),
+            4 => MatchFormat::SuddenDeath,
+            _ => Self::DEFAULT.format,
+        };
+        self.leds = LedTimings {
+            countdown_ms: input.u16()?,
+            score_blink_ms: input.u16()?,
+            false_start_strobe_ms: input.u16()?,
+        };
+        self.foreperiod = input.foreperiod()?.unwrap_or(Self::DEFAULT.foreperiod);
+        self.practice_foreperiod = input
+            .foreperiod()?
+            .unwrap_or(Self::DEFAULT.practice_foreperiod);
+        self.bot = input.bot()?;
+        for kind in self.debouncers.iter_mut() {
+            *kind = match input.u8()? {
+                0 => DebounceKind::TimeLock,
+                1 => DebounceKind::Integrator,
+                _ => DebounceKind::SleepVerify,
+            };
+        }
+        Some(())
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+// Kind no version reads, for a foreperiod the record can't hold
+const UNSAVED_FOREPERIOD: u8 = 0xFF;
+
+struct Writer<'a> {
+    bytes: &'a mut [u8],
+    at: usize,
+}
+
+impl Writer<'_> {
+    fn put(&mut self, bytes: &[u8]) {
+        self.bytes[self.at..self.at + bytes.len()].copy_from_slice(bytes);
+        self.at += bytes.len();
+    }
+
+    fn u8(&mut self, value: u8) {
+        self.put(&[value]);
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.put(&value.to_le_bytes());
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.put(&value.to_le_bytes());
+    }
+
+    fn micros(&mut self, value: Micros) {
+        self.u32(value.min(u32::MAX as Micros) as u32);
+    }
+
+    fn foreperiod(&mut self, foreperiod: Foreperiod) {
+        let (kind, words) = match foreperiod {
+            Foreperiod::Uniform { min, max } => (0, [min, max, 0]),
+            Foreperiod::Exponential { min, mean, max } => (1, [min, mean, max]),
+            Foreperiod::Geometric {
+                min,
+                step,
+                hazard_per_mille,
+                max_steps,
+            } => (
+                2,
+                [
+                    min,
+                    step,
+                    (hazard_per_mille as Micros) << 16 | max_steps as Micros,
+                ],
+            ),
+            Foreperiod::Fixed(_) => (UNSAVED_FOREPERIOD, [0; 3]),
+        };
+        self.u8(kind);
+        for word in words {
+            self.micros(word);
+        }
+    }
+
+    fn bot(&mut self, bot: Option<ReactionDistribution>) {
+        let (kind, words) = match bot {
+            None => (0, [0; 2]),
+            Some(ReactionDistribution::Uniform { min, max }) => (1, [min, max]),
+            Some(ReactionDistribution::Normal { mean, std_dev }) => (2, [mean, std_dev]),
+        };
+        self.u8(kind);
+        for word in words {
+            self.micros(word);
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let (taken, rest) = self.bytes.split_first_chunk()?;
+        self.bytes = rest;
+        Some(*taken)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.take().map(u8::from_le_bytes)
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.take().map(u16::from_le_bytes)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.take().map(u32::from_le_bytes)
+    }
+
+    // `Some(None)` for a kind this version doesn't know
+    fn foreperiod(&mut self) -> Option<Option<Foreperiod>> {
+        let kind = self.u8()?;
+        let [a, b, c] = [self.u32()?, self.u32()?, self.u32()?].map(Micros::from);
+        Some(match kind {
+            0 => Some(Foreperiod::Uniform { min: a, max: b }),
+            1 => Some(Foreperiod::Exponential {
+                min: a,
+                mean: b,
+                max: c,
+            }),
+            2 => Some(Foreperiod::Geometric {
+                min: a,
+                step: b,
+                hazard_per_mille: (c >> 16) as u16,
+                max_steps: c as u16,
+            }),
+            _ => None,
+        })
+    }
+
+    // No bot for a kind this version doesn't know
+    fn bot(&mut self) -> Option<Option<ReactionDistribution>> {
+        let kind = self.u8()?;
+        let [a, b] = [self.u32()?, self.u32()?].map(Micros::from);
+        Some(match kind {
+            1 => Some(ReactionDistribution::Uniform { min: a, max: b }),
+            2 => Some(ReactionDistribution::Normal {
+                mean: a,
+                std_dev: b,
+            }),
+            _ => None,
+        })
+    }
+}
+
+/// A config field reachable from the console, by the name [`Self::NAMED`] gives it
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Setting {
+    DebounceMs,
+    WatchdogStarveMs,
+    WatchdogFeedMs,
+    Format,
+    CountdownMs,
+    ScoreBlinkMs,
+    FalseStartStrobeMs,
+    Foreperiod,
+    PracticeForeperiod,
+    Bot,
+    /// Named `p1.button` and so on
+    Button(ButtonRole),
+    /// Named `p1.led` and so on
+    Led(ButtonRole),
+    /// Named `p1.debouncer` and so on
+    Debouncer(ButtonRole),
+}
+
+/// Value of a [`Setting`], foreperiods and bot reactions in ms
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Value {
+    Number(u32),
+    Format(MatchFormat),
+    Foreperiod(Foreperiod),
+    Bot(Option<ReactionDistribution>),
+    Debouncer(DebounceKind),
+}
+
+impl Setting {
+    /// Every setting but the per-player ones
+    pub const NAMED: [(&'static str, Setting); 10] = [
+        ("debounce_ms", Setting::DebounceMs),
+        ("watchdog_starve_ms", Setting::WatchdogStarveMs),
+        ("watchdog_feed_ms", Setting::WatchdogFeedMs),
+        ("format", Setting::Format),
+        ("countdown_ms", Setting::CountdownMs),
+        ("score_blink_ms", Setting::ScoreBlinkMs),
+        ("false_start_strobe_ms", Setting::FalseStartStrobeMs),
+        ("foreperiod", Setting::Foreperiod),
+        ("practice_foreperiod", Setting::PracticeForeperiod),
+        ("bot", Setting::Bot),
+    ];
+
+    /// Named settings then the ones of the first `players`
+    pub fn all(players: usize) -> impl Iterator<Item = Setting> {
+        Self::NAMED.into_iter().map(|(_, setting)| setting).chain(
+            ButtonRole::first(players).flat_map(|player| {
+                [
+                    Setting::Button(player),
+                    Setting::Led(player),
+                    Setting::Debouncer(player),
+                ]
+            }),
+        )
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        if let Some((_, setting)) = Self::NAMED.iter().find(|(named, _)| *named == name) {
+            return Some(*setting);
+        }
+        let (player, pin) = name.strip_prefix('p')?.split_once('.')?;
+        let player = ButtonRole::new(player.parse::<usize>().ok()?.checked_sub(1)?)?;
+        match pin {
+            "button" => Some(Setting::Button(player)),
+            "led" => Some(Setting::Led(player)),
+            "debouncer" => Some(Setting::Debouncer(player)),
+            _ => None,
+        }
+    }
+
+    /// Only read at boot, a change waits for the next one
+    pub fn applies_at_boot(self) -> bool {
+        !matches!(
+            self,
+            Setting::CountdownMs | Setting::ScoreBlinkMs | Setting::FalseStartStrobeMs
+        )
+    }
+}
+
+impl core::fmt::Display for Setting {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Setting::Button(player) => write!(f, "p{}.button", player.index() + 1),
+            Setting::Led(player) => write!(f, "p{}.led", player.index() + 1),
+            Setting::Debouncer(player) => write!(f, "p{}.debouncer", player.index() + 1),
+            named => {
+                let (name, _) = Self::NAMED.iter().find(|(_, s)| s == named).unwrap();
+                f.write_str(name)
+            }
+        }
+    }
+}
+
+/// As typed in the console, e.g. `exponential 1500 1500 8000` or `normal 400 60`
+///
+/// A fixed foreperiod has no such form, formatting one fails, a config never holds one.
+impl core::fmt::Display for Value {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let ms = as_millis;
+        match *self {
+            Value::Number(n) => write!(f, "{n}"),
+            Value::Format(format) => write!(f, "{format}"),
+            Value::Foreperiod(Foreperiod::Uniform { min, max }) => {
+                write!(f, "uniform {} {}", ms(min), ms(max))
+            }
+            Value::Foreperiod(Foreperiod::Exponential { min, mean, max }) => {
+                write!(f, "exponential {} {} {}", ms(min), ms(mean), ms(max))
+            }
+            Value::Foreperiod(Foreperiod::Geometric {
+                min,
+                step,
+                hazard_per_mille,
+                max_steps,
+            }) => write!(
+                f,
+                "geometric {} {} {hazard_per_mille} {max_steps}",
+                ms(min),
+                ms(step)
+            ),
+            Value::Foreperiod(Foreperiod::Fixed(_)) => Err(core::fmt::Error),
+            Value::Bot(None) => f.write_str("none"),
+            Value::Bot(Some(ReactionDistribution::Uniform { min, max })) => {
+                write!(f, "uniform {} {}", ms(min), ms(max))
+            }
+            Value::Bot(Some(ReactionDistribution::Normal { mean, std_dev })) => {
+                write!(f, "normal {} {}", ms(mean), ms(std_dev))
+            }
+            Value::Debouncer(kind) => write!(f, "{kind}"),
+        }
+    }
+}
+
+impl Config {
+    pub fn get(&self, setting: Setting) -> Value {
+        match setting {
+            Setting::DebounceMs => Value::Number(self.debounce_ms as u32),
+            Setting::WatchdogStarveMs => Value::Number(self.watchdog_starve_ms),
+            Setting::WatchdogFeedMs => Value::Number(self.watchdog_feed_ms),
+            Setting::Format => Value::Format(self.format),
+            Setting::CountdownMs => Value::Number(self.leds.countdown_ms as u32),
+            Setting::ScoreBlinkMs => Value::Number(self.leds.score_blink_ms as u32),
+            Setting::FalseStartStrobeMs => Value::Number(self.leds.false_start_strobe_ms as u32),
+            Setting::Foreperiod => Value::Foreperiod(self.foreperiod),
+            Setting::PracticeForeperiod => Value::Foreperiod(self.practice_foreperiod),
+            Setting::Bot => Value::Bot(self.bot),
+            Setting::Button(player) => Value::Number(self.pins[player.index()].button as u32),
+            Setting::Led(player) => Value::Number(self.pins[player.index()].led as u32),
+            Setting::Debouncer(player) => Value::Debouncer(self.debouncers[player.index()]),
+        }
+    }
+
+    /// False when the value doesn't suit the setting, e.g. out of range, the config is left alone
+    pub fn set(&mut self, setting: Setting, value: Value) -> bool {
+        match (setting, value) {
+            (Setting::Format, Value::Format(format)) => self.format = format,
+            // Its set of values couldn't be saved
+            (_, Value::Foreperiod(Foreperiod::Fixed(_))) => return false,
+            (Setting::Foreperiod, Value::Foreperiod(foreperiod)) => self.foreperiod = foreperiod,
+            (Setting::PracticeForeperiod, Value::Foreperiod(foreperiod)) => {
+                self.practice_foreperiod = foreperiod
+            }
+            (Setting::Bot, Value::Bot(bot)) => self.bot = bot,
+            (Setting::Debouncer(player), Value::Debouncer(kind)) => {
+                self.debouncers[player.index()] = kind
+            }
+            (Setting::WatchdogStarveMs, Value::Number(n)) => self.watchdog_starve_ms = n,
+            (Setting::WatchdogFeedMs, Value::Number(n)) => self.watchdog_feed_ms = n,
+            (setting, Value::Number(n)) => {
+                let (ms, pin) = (u16::try_from(n).ok(), u8::try_from(n).ok());
+                let done = match setting {
+                    Setting::DebounceMs => ms.map(|ms| self.debounce_ms = ms),
+                    Setting::CountdownMs => ms.map(|ms| self.leds.countdown_ms = ms),
+                    Setting::ScoreBlinkMs => ms.map(|ms| self.leds.score_blink_ms = ms),
+                    Setting::FalseStartStrobeMs => {
+                        ms.map(|ms| self.leds.false_start_strobe_ms = ms)
+                    }
+                    Setting::Button(player) => {
+                        pin.map(|pin| self.pins[player.index()].button = pin)
+                    }
+                    Setting::Led(player) => pin.map(|pin| self.pins[player.index()].led = pin),
+                    _ => None,
+                };
+                return done.is_some();
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// This config with `setting` changed to `value`, `None` when either doesn't suit the
+    /// board, as [`Self::is_valid`] tells
+    pub fn with_setting(
+        mut self,
+        setting: Setting,
+        value: Value,
+        players: usize,
+        reserved: &[u8],
+    ) -> Option<Self> {
+        (self.set(setting, value) && self.is_valid(players, reserved)).then_some(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+
+    // The record of `config` with a field unknown here appended, as a later build saves it
+    fn appended_record(config: &Config, version: u16) -> [u8; Config::SIZE + 4] {
+        let mut bytes = [0; Config::SIZE + 4];
+        bytes[..Config::SIZE].copy_from_slice(&config.to_bytes());
+        bytes[HEADER + PAYLOAD..].copy_from_slice(&[1, 2, 3, 4]);
+        bytes[4..6].copy_from_slice(&version.to_le_bytes());
+        bytes[6..8].copy_from_slice(&(PAYLOAD as u16 + 4).to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn default_matches_the_reference_board() {
+        assert!(Config::DEFAULT.is_valid(MAX_PLAYERS, &[25]));
+        assert_eq!(Config::DEFAULT.pins[1], PlayerPins { button: 11, led: 8 });
+        assert_eq!(Config::DEFAULT.watchdog_starve_ms, 3_000);
+    }
+
+    #[test]
+    fn config_round_trips_through_bytes() {
+        let mut config = Config::DEFAULT;
+        config.pins[0] = PlayerPins { button: 2, led: 3 };
+        config.format = MatchFormat::WinByTwo(4);
+        config.leds.score_blink_ms = 300;
+        config.bot = Some(ReactionDistribution::HARD);
+        config.debouncers[1] = DebounceKind::Integrator;
+        config.debouncers[7] = DebounceKind::TimeLock;
+        let geometric = Foreperiod::Geometric {
+            min: 1_000_000,
+            step: 250_000,
+            hazard_per_mille: 150,
+            max_steps: 12,
+        };
+        // Every kind a config can hold, in both slots
+        for (foreperiod, practice_foreperiod) in [
+            (geometric, Foreperiod::CLASSIC),
+            (Foreperiod::CLASSIC, Foreperiod::NON_AGING),
+            (Foreperiod::NON_AGING, geometric),
+        ] {
+            config.foreperiod = foreperiod;
+            config.practice_foreperiod = practice_foreperiod;
+            assert!(config.is_valid(MAX_PLAYERS - 1, &[]));
+            assert_eq!(
+                Config::from_bytes(&config.to_bytes()),
+                Some((config, Config::VERSION))
+            );
+        }
+
+        assert_eq!(Config::from_bytes(&[0xFF; Config::SIZE]), None);
+        assert_eq!(Config::from_bytes(b"BWCF"), None);
+        assert_eq!(
+            Config::from_bytes(&config.to_bytes()[..Config::SIZE - 1]),
+            None
+        );
+    }
+
+    #[test]
+    fn appended_fields_keep_the_known_ones() {
+        let mut config = Config::DEFAULT;
+        config.debounce_ms = 35;
+        config.leds.countdown_ms = 400;
+        // Fields are appended without a version bump
+        assert_eq!(
+            Config::from_bytes(&appended_record(&config, Config::VERSION)),
+            Some((config, Config::VERSION))
+        );
+    }
+
+    #[test]
+    fn newer_versions_keep_the_known_fields() {
+        let mut config = Config::DEFAULT;
+        config.debounce_ms = 35;
+        assert_eq!(
+            Config::from_bytes(&appended_record(&config, Config::VERSION + 1)),
+            Some((config, Config::VERSION + 1))
+        );
+    }
+
+    #[test]
+    fn invalid_pins_are_caught() {
+        let mut config = Config::DEFAULT;
+        config.pins[1].led = 10;
+        assert!(!config.is_valid(2, &[]));
+        assert!(Config::DEFAULT.is_valid(2, &[]));
+        assert!(!Config::DEFAULT.is_valid(2, &[5]));
+        config = Config::DEFAULT;
+        config.pins[0].button = 30;
+        assert!(!config.is_valid(1, &[]));
+        // Pins of players not in use don't matter
+        config = Config::DEFAULT;
+        config.pins[7].button = 30;
+        assert!(config.is_valid(2, &[]));
+        config = Config::DEFAULT;
+        config.watchdog_feed_ms = 2_000;
+        assert!(!config.is_valid(2, &[]));
+    }
+
+    #[test]
+    fn a_bot_takes_a_seat() {
+        let mut config = Config::DEFAULT;
+        // A lone player needs someone to play against
+        assert!(!config.is_valid(1, &[]));
+        config.bot = Some(ReactionDistribution::EASY);
+        assert!(config.is_valid(1, &[]));
+        assert!(!config.is_valid(0, &[]));
+        // Every seat taken by a button leaves none for it
+        assert!(config.is_valid(MAX_PLAYERS - 1, &[]));
+        assert!(!config.is_valid(MAX_PLAYERS, &[]));
+
+        config.bot = Some(ReactionDistribution::Uniform {
+            min: 300_000,
+            max: 200_000,
+        });
+        assert!(!config.is_valid(1, &[]));
+        // Never pressing in time is no opponent
+        config.bot = Some(ReactionDistribution::Normal {
+            mean: 400_000,
+            std_dev: Config::MAX_BOT_REACTION,
+        });
+        assert!(!config.is_valid(1, &[]));
+    }
+
+    #[test]
+    fn watchdog_times_the_chip_can_arm() {
+        let mut config = Config::DEFAULT;
+        config.watchdog_starve_ms = Config::MAX_WATCHDOG_STARVE_MS;
+        assert!(config.is_valid(2, &[]));
+        // Would panic arming the watchdog on every boot
+        config.watchdog_starve_ms = 10_000;
+        assert!(!config.is_valid(2, &[]));
+        config = Config::DEFAULT;
+        config.watchdog_feed_ms = 0;
+        assert!(!config.is_valid(2, &[]));
+    }
+
+    #[test]
+    fn fixed_foreperiods_are_refused() {
+        let fixed = Foreperiod::Fixed(&[2_000_000, 3_000_000]);
+        let mut config = Config::DEFAULT;
+        assert!(!config.set(Setting::Foreperiod, Value::Foreperiod(fixed)));
+        assert_eq!(config, Config::DEFAULT);
+        config.practice_foreperiod = fixed;
+        assert!(!config.is_valid(2, &[]));
+        // Not read back as some other distribution
+        let (saved, _) = Config::from_bytes(&config.to_bytes()).unwrap();
+        assert_eq!(
+            saved.practice_foreperiod,
+            Config::DEFAULT.practice_foreperiod
+        );
+    }
+
+    #[test]
+    fn timings_past_their_cap_are_refused() {
+        let cap = Config::MAX_TIMING_MS;
+        let fields: [fn(&mut Config) -> &mut u16; 4] = [
+            |config| &mut config.debounce_ms,
+            |config| &mut config.leds.countdown_ms,
+            |config| &mut config.leds.score_blink_ms,
+            |config| &mut config.leds.false_start_strobe_ms,
+        ];
+        for (i, field) in fields.into_iter().enumerate() {
+            let mut config = Config::DEFAULT;
+            *field(&mut config) = cap;
+            assert!(config.is_valid(2, &[]), "field {i}");
+            *field(&mut config) = cap + 1;
+            assert!(!config.is_valid(2, &[]), "field {i}");
+        }
+    }
+
+    #[test]
+    fn formats_without_a_round_are_refused() {
+        for format in [
+            MatchFormat::BestOf(0),
+            MatchFormat::FirstTo(0),
+            MatchFormat::WinByTwo(0),
+            MatchFormat::TotalTime(0),
+        ] {
+            let mut config = Config::DEFAULT;
+            config.format = format;
+            assert!(!config.is_valid(2, &[]), "{format}");
+            // Nor loaded back from a record
+            let (saved, _) = Config::from_bytes(&config.to_bytes()).unwrap();
+            assert!(!saved.is_valid(2, &[]), "{format}");
+        }
+        let mut config = Config::DEFAULT;
+        config.format = MatchFormat::SuddenDeath;
+        assert!(config.is_valid(2, &[]));
+    }
+
+    #[test]
+    fn foreperiods_out_of_bounds_are_refused() {
+        let refused = [
+            // Ending before they start
+            Foreperiod::Uniform {
+                min: 5_000_000,
+                max: 2_000_000,
+            },
+            Foreperiod::Exponential {
+                min: 3_000_000,
+                mean: 1_000_000,
+                max: 2_000_000,
+            },
+            // Holding past the cap
+            Foreperiod::Uniform {
+                min: 2_000_000,
+                max: Config::MAX_HOLD + 1,
+            },
+            Foreperiod::Geometric {
+                min: 1_000_000,
+                step: 1_000_000,
+                hazard_per_mille: 100,
+                max_steps: 10,
+            },
+        ];
+        for foreperiod in refused {
+            let mut config = Config::DEFAULT;
+            config.foreperiod = foreperiod;
+            assert!(!config.is_valid(2, &[]), "{foreperiod:?}");
+            config = Config::DEFAULT;
+            config.practice_foreperiod = foreperiod;
+            assert!(!config.is_valid(2, &[]), "{foreperiod:?}");
+        }
+        // The holds of the formats are within the cap as well
+        for format in MatchFormat::PRESETS {
+            if let Some(foreperiod) = format.foreperiod() {
+                assert!(foreperiod.longest() <= Config::MAX_HOLD, "{format}");
+            }
+        }
+    }
+
+    fn geometric(step: Micros, hazard_per_mille: u16, max_steps: u16) -> Foreperiod {
+        Foreperiod::Geometric {
+            min: 1_000_000,
+            step,
+            hazard_per_mille,
+            max_steps,
+        }
+    }
+
+    #[test]
+    fn foreperiods_that_cannot_draw_are_refused() {
+        let with = |foreperiod| Config {
+            foreperiod,
+            ..Config::DEFAULT
+        };
+        // GO would never come on a step, or come more than always
+        assert!(!with(geometric(250_000, 0, 8)).is_valid(2, &[]));
+        assert!(!with(geometric(250_000, 1001, 8)).is_valid(2, &[]));
+        assert!(with(geometric(250_000, 1000, 8)).is_valid(2, &[]));
+        // Steps of no time all land on the minimum
+        assert!(!with(geometric(0, 200, 8)).is_valid(2, &[]));
+        assert!(with(geometric(0, 200, 0)).is_valid(2, &[]));
+        // An exponential wait of mean 0 is no wait
+        let exponential = |mean| Foreperiod::Exponential {
+            min: 1_000_000,
+            mean,
+            max: 4_000_000,
+        };
+        assert!(!with(exponential(0)).is_valid(2, &[]));
+        assert!(with(exponential(1)).is_valid(2, &[]));
+    }
+
+    #[test]
+    fn settings_by_name() {
+        let names: [&str; 5] = [
+            "debounce_ms",
+            "p2.led",
+            "practice_foreperiod",
+            "p8.button",
+            "p3.debouncer",
+        ];
+        for name in names {
+            let setting = Setting::parse(name).unwrap();
+            assert_eq!(std::format!("{setting}"), name);
+        }
+        assert_eq!(
+            Setting::parse("p1.button"),
+            Some(Setting::Button(ButtonRole::PLAYER_1))
+        );
+        for name in ["p0.led", "p9.led", "p1.pin", "debounce", ""] {
+            assert_eq!(Setting::parse(name), None, "{name}");
+        }
+        assert_eq!(Setting::all(2).count(), Setting::NAMED.len() + 6);
+    }
+
+    #[test]
+    fn settings_read_and_write_the_config() {
+        let mut config = Config::DEFAULT;
+        assert!(config.set(Setting::DebounceMs, Value::Number(300)));
+        assert!(config.set(Setting::Led(ButtonRole::PLAYER_2), Value::Number(21)));
+        assert!(config.set(Setting::Format, Value::Format(MatchFormat::SuddenDeath)));
+        assert_eq!(config.get(Setting::DebounceMs), Value::Number(300));
+        assert_eq!(config.pins[1].led, 21);
+        assert_eq!(config.format, MatchFormat::SuddenDeath);
+
+        // Out of range or of the wrong kind, nothing changes
+        assert!(!config.set(Setting::Button(ButtonRole::PLAYER_1), Value::Number(256)));
+        assert!(!config.set(Setting::CountdownMs, Value::Number(70_000)));
+        assert!(!config.set(Setting::Foreperiod, Value::Number(3)));
+        assert_eq!(config.pins[0], Config::DEFAULT.pins[0]);
+
+        assert_eq!(
+            std::format!("{}", Config::DEFAULT.get(Setting::Foreperiod)),
+            "exponential 1500 1500 8000"
+        );
+    }
+}
@@ -0,0 +1,584 @@
+use core::str::SplitAsciiWhitespace;
+
+use heapless::Vec;
+
+use crate::{
+    config::{Setting, Value},
+    ButtonRole, DebounceKind, Foreperiod, GameEvent, GameState, MatchFormat, Micros,
+    ReactionDistribution, MAX_PLAYERS,
+};
+
+/// Longest command line, longer ones are refused whole
+pub const MAX_LINE: usize = 80;
+
+/// Help printed by the console, one command per line
+pub const HELP: &str = "\
+help                    this list
+status                  state, format and scores
+start                   start a match from the waiting menu
+reset                   abort whatever is going on, back to waiting
+stats                   stats of the last match
+records                 all-time records
+mode [FORMAT]           show or pick the format: best-of N, first-to N, win-by-two N, total-time N, sudden-death
+config get [NAME]       show the config, or one setting
+config set NAME VALUE   change and save a setting";
+
+/// A console command line, parsed
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Command {
+    Help,
+    Status,
+    Start,
+    Reset,
+    Stats,
+    Records,
+    /// Pick a format, or show it with `None`
+    Mode(Option<MatchFormat>),
+    /// One setting, or all of them with `None`
+    ConfigGet(Option<Setting>),
+    ConfigSet(Setting, Value),
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ParseError {
+    TooLong,
+    UnknownCommand,
+    UnknownSetting,
+    UnknownFormat,
+    UnknownForeperiod,
+    UnknownBot,
+    UnknownDebouncer,
+    MissingArgument,
+    BadNumber,
+    TooManyArguments,
+}
+
+impl core::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            ParseError::TooLong => "line too long",
+            ParseError::UnknownCommand => "unknown command, try help",
+            ParseError::UnknownSetting => "unknown setting, config get lists them",
+            ParseError::UnknownFormat => "unknown format, try help",
+            ParseError::UnknownForeperiod => {
+                "unknown foreperiod, uniform MIN MAX, exponential MIN MEAN MAX or geometric MIN STEP HAZARD STEPS"
+            }
+            ParseError::UnknownBot => "unknown bot, none, uniform MIN MAX or normal MEAN STD_DEV",
+            ParseError::UnknownDebouncer => "unknown debouncer, time-lock, integrator or sleep-verify",
+            ParseError::MissingArgument => "missing argument",
+            ParseError::BadNumber => "not a number in range",
+            ParseError::TooManyArguments => "too many arguments",
+        })
+    }
+}
+
+impl Command {
+    /// `None` for a blank line
+    pub fn parse(line: &str) -> Result<Option<Self>, ParseError> {
+        let mut words = line.split_ascii_whitespace();
+        let Some(name) = words.next() else {
+            return Ok(None);
+        };
+        let command = match name {
+            "help" => Command::Help,
+            "status" => Command::Status,
+            "start" => Command::Start,
+            "reset" => Command::Reset,
+            "stats" => Command::Stats,
+            "records" => Command::Records,
+            "mode" => match words.clone().next() {
+                Some(_) => Command::Mode(Some(format(&mut words)?)),
+                None => Command::Mode(None),
+            },
+            "config" => match words.next().ok_or(ParseError::MissingArgument)? {
+                "get" => Command::ConfigGet(words.next().map(setting).transpose()?),
+                "set" => {
+                    let setting = setting(words.next().ok_or(ParseError::MissingArgument)?)?;
+                    Command::ConfigSet(setting, value(setting, &mut words)?)
+                }
+                _ => return Err(ParseError::UnknownCommand),
+            },
+            _ => return Err(ParseError::UnknownCommand),
+        };
+        match words.next() {
+            Some(_) => Err(ParseError::TooManyArguments),
+            None => Ok(Some(command)),
+        }
+    }
+}
+
+fn setting(name: &str) -> Result<Setting, ParseError> {
+    Setting::parse(name).ok_or(ParseError::UnknownSetting)
+}
+
+fn number<T: core::str::FromStr>(words: &mut SplitAsciiWhitespace) -> Result<T, ParseError> {
+    words
+        .next()
+        .ok_or(ParseError::MissingArgument)?
+        .parse()
+        .map_err(|_| ParseError::BadNumber)
+}
+
+fn ms(words: &mut SplitAsciiWhitespace) -> Result<Micros, ParseError> {
+    number::<u32>(words).map(|ms| ms as Micros * 1_000)
+}
+
+fn format(words: &mut SplitAsciiWhitespace) -> Result<MatchFormat, ParseError> {
+    let format = match words.next().ok_or(ParseError::MissingArgument)? {
+        "sudden-death" => return Ok(MatchFormat::SuddenDeath),
+        "best-of" => MatchFormat::BestOf,
+        "first-to" => MatchFormat::FirstTo,
+        "win-by-two" => MatchFormat::WinByTwo,
+        "total-time" => MatchFormat::TotalTime,
+        _ => return Err(ParseError::UnknownFormat),
+    };
+    match number(words)? {
+        0 => Err(ParseError::BadNumber),
+        rounds => Ok(format(rounds)),
+    }
+}
+
+fn value(setting: Setting, words: &mut SplitAsciiWhitespace) -> Result<Value, ParseError> {
+    Ok(match setting {
+        Setting::Format => Value::Format(format(words)?),
+        Setting::Foreperiod | Setting::PracticeForeperiod => {
+            let foreperiod = match words.next().ok_or(ParseError::MissingArgument)? {
+                "uniform" => Foreperiod::Uniform {
+                    min: ms(words)?,
+                    max: ms(words)?,
+                },
+                "exponential" => Foreperiod::Exponential {
+                    min: ms(words)?,
+                    mean: ms(words)?,
+                    max: ms(words)?,
+                },
+                "geometric" => Foreperiod::Geometric {
+                    min: ms(words)?,
+                    step: ms(words)?,
+                    hazard_per_mille: number(words)?,
+                    max_steps: number(words)?,
+                },
+                _ => return Err(ParseError::UnknownForeperiod),
+            };
+            Value::Foreperiod(foreperiod)
+        }
+        Setting::Bot => Value::Bot(match words.next().ok_or(ParseError::MissingArgument)? {
+            "none" => None,
+            "uniform" => Some(ReactionDistribution::Uniform {
+                min: ms(words)?,
+                max: ms(words)?,
+            }),
+            "normal" => Some(ReactionDistribution::Normal {
+                mean: ms(words)?,
+                std_dev: ms(words)?,
+            }),
+            _ => return Err(ParseError::UnknownBot),
+        }),
+        Setting::Debouncer(_) => {
+            let name = words.next().ok_or(ParseError::MissingArgument)?;
+            Value::Debouncer(DebounceKind::parse(name).ok_or(ParseError::UnknownDebouncer)?)
+        }
+        _ => Value::Number(number(words)?),
+    })
+}
+
+/// Bytes typed in the console, gathered into lines
+#[derive(Default)]
+pub struct LineBuffer {
+    line: Vec<u8, MAX_LINE>,
+    too_long: bool,
+    /// The line was handed out, the next byte starts a new one
+    done: bool,
+}
+
+impl LineBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The line once Enter ends it, backspace erases and other control bytes are dropped
+    pub fn push(&mut self, byte: u8) -> Option<Result<&str, ParseError>> {
+        if self.done {
+            self.line.clear();
+            self.too_long = false;
+            self.done = false;
+        }
+        match byte {
+            // A CR LF pair ends one line, the LF finds it empty
+            b'\r' | b'\n' if self.line.is_empty() && !self.too_long => None,
+            b'\r' | b'\n' => {
+                self.done = true;
+                match self.too_long {
+                    true => Some(Err(ParseError::TooLong)),
+                    // Only printable ASCII gets in
+                    false => core::str::from_utf8(&self.line).ok().map(Ok),
+                }
+            }
+            0x08 | 0x7F => {
+                self.line.pop();
+                None
+            }
+            b' '..=b'~' => {
+                self.too_long |= self.line.push(byte).is_err();
+                None
+            }
+            _ => None,
+        }
+    }
+}
+
+/// What `status` reports, followed from the game events
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct GameStatus {
+    pub state: GameState,
+    pub format: MatchFormat,
+    /// Round being played, from 0
+    pub round: Option<usize>,
+    /// Round wins of the match being played or the last one, by [`ButtonRole::index`]
+    pub scores: [usize; MAX_PLAYERS],
+    pub last_winner: Option<ButtonRole>,
+}
+
+impl GameStatus {
+    pub const fn new(format: MatchFormat) -> Self {
+        Self {
+            state: GameState::Waiting,
+            format,
+            round: None,
+            scores: [0; MAX_PLAYERS],
+            last_winner: None,
+        }
+    }
+
+    pub fn on_event(&mut self, event: &GameEvent) {
+        match *event {
+            GameEvent::StateChanged { to, .. } => {
+                self.state = to;
+                if to == GameState::Playing {
+                    self.round = None;
+                    self.scores = [0; MAX_PLAYERS];
+                    self.last_winner = None;
+                }
+            }
+            GameEvent::RoundStarted { index } => self.round = Some(index),
+            GameEvent::RoundWon(outcome) => {
+                if let Some(winner) = outcome.result.winner {
+                    self.scores[winner.index()] = outcome.winner_score;
+                }
+            }
+            GameEvent::MatchWon { winner } => self.last_winner = Some(winner),
+            GameEvent::FormatChanged { format } => self.format = format,
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use std::{string::String, vec::Vec};
+
+    use super::*;
+    use crate::{Config, PlayerResult, PlayerResults, RoundOutcome, RoundResult, Verdict};
+
+    fn parse(line: &str) -> Result<Option<Command>, ParseError> {
+        Command::parse(line)
+    }
+
+    // Lines typed byte by byte
+    fn lines(typed: &[u8]) -> Vec<Result<String, ParseError>> {
+        let mut buffer = LineBuffer::new();
+        typed
+            .iter()
+            .filter_map(|&byte| buffer.push(byte).map(|line| line.map(String::from)))
+            .collect()
+    }
+
+    #[test]
+    fn simple_commands() {
+        assert_eq!(parse("status"), Ok(Some(Command::Status)));
+        assert_eq!(parse("  start  "), Ok(Some(Command::Start)));
+        assert_eq!(parse("reset"), Ok(Some(Command::Reset)));
+        assert_eq!(parse("stats"), Ok(Some(Command::Stats)));
+        assert_eq!(parse("records"), Ok(Some(Command::Records)));
+        assert_eq!(parse("help"), Ok(Some(Command::Help)));
+        assert_eq!(parse(""), Ok(None));
+        assert_eq!(parse("   "), Ok(None));
+        assert_eq!(parse("launch"), Err(ParseError::UnknownCommand));
+        assert_eq!(parse("status now"), Err(ParseError::TooManyArguments));
+    }
+
+    #[test]
+    fn help_lists_every_command() {
+        let lines = [
+            "help",
+            "status",
+            "start",
+            "reset",
+            "stats",
+            "records",
+            "mode",
+            "config get",
+            "config set debounce_ms 30",
+        ];
+        for line in lines {
+            // A new command doesn't build here until it's named, then listed
+            let name = match parse(line).unwrap().unwrap() {
+                Command::Help => "help",
+                Command::Status => "status",
+                Command::Start => "start",
+                Command::Reset => "reset",
+                Command::Stats => "stats",
+                Command::Records => "records",
+                Command::Mode(_) => "mode",
+                Command::ConfigGet(_) => "config get",
+                Command::ConfigSet(..) => "config set",
+            };
+            assert!(
+                HELP.lines().any(|help| help
+                    .strip_prefix(name)
+                    .is_some_and(|rest| rest.starts_with(' '))),
+                "{name} missing from the help"
+            );
+        }
+    }
+
+    #[test]
+    fn mode_shows_or_picks_a_format() {
+        assert_eq!(parse("mode"), Ok(Some(Command::Mode(None))));
+        assert_eq!(
+            parse("mode win-by-two 3"),
+            Ok(Some(Command::Mode(Some(MatchFormat::WinByTwo(3)))))
+        );
+        assert_eq!(
+            parse("mode sudden-death"),
+            Ok(Some(Command::Mode(Some(MatchFormat::SuddenDeath))))
+        );
+        assert_eq!(parse("mode best-of"), Err(ParseError::MissingArgument));
+        assert_eq!(parse("mode best-of 0"), Err(ParseError::BadNumber));
+        assert_eq!(parse("mode best-of 300"), Err(ParseError::BadNumber));
+        assert_eq!(parse("mode marathon 3"), Err(ParseError::UnknownFormat));
+        assert_eq!(
+            parse("mode sudden-death 1"),
+            Err(ParseError::TooManyArguments)
+        );
+        // Every preset reads back from how it's shown
+        for format in MatchFormat::PRESETS {
+            let line = std::format!("mode {format}");
+            assert_eq!(parse(&line), Ok(Some(Command::Mode(Some(format)))));
+        }
+    }
+
+    #[test]
+    fn config_get_and_set() {
+        assert_eq!(parse("config get"), Ok(Some(Command::ConfigGet(None))));
+        assert_eq!(
+            parse("config get p2.button"),
+            Ok(Some(Command::ConfigGet(Some(Setting::Button(
+                ButtonRole::PLAYER_2
+            )))))
+        );
+        assert_eq!(
+            parse("config set debounce_ms 30"),
+            Ok(Some(Command::ConfigSet(
+                Setting::DebounceMs,
+                Value::Number(30)
+            )))
+        );
+        assert_eq!(
+            parse("config set foreperiod uniform 2000 5000"),
+            Ok(Some(Command::ConfigSet(
+                Setting::Foreperiod,
+                Value::Foreperiod(Foreperiod::CLASSIC)
+            )))
+        );
+        assert_eq!(
+            parse("config set format first-to 7"),
+            Ok(Some(Command::ConfigSet(
+                Setting::Format,
+                Value::Format(MatchFormat::FirstTo(7))
+            )))
+        );
+        assert_eq!(parse("config"), Err(ParseError::MissingArgument));
+        assert_eq!(parse("config get nope"), Err(ParseError::UnknownSetting));
+        assert_eq!(
+            parse("config set debounce_ms"),
+            Err(ParseError::MissingArgument)
+        );
+        assert_eq!(
+            parse("config set debounce_ms fast"),
+            Err(ParseError::BadNumber)
+        );
+        assert_eq!(
+            parse("config set foreperiod normal 1 2"),
+            Err(ParseError::UnknownForeperiod)
+        );
+        assert_eq!(
+            parse("config set bot normal 400 60"),
+            Ok(Some(Command::ConfigSet(
+                Setting::Bot,
+                Value::Bot(Some(ReactionDistribution::EASY))
+            )))
+        );
+        assert_eq!(
+            parse("config set bot none"),
+            Ok(Some(Command::ConfigSet(Setting::Bot, Value::Bot(None))))
+        );
+        assert_eq!(
+            parse("config set bot exponential 1 2"),
+            Err(ParseError::UnknownBot)
+        );
+        assert_eq!(
+            parse("config set p2.debouncer integrator"),
+            Ok(Some(Command::ConfigSet(
+                Setting::Debouncer(ButtonRole::PLAYER_2),
+                Value::Debouncer(DebounceKind::Integrator)
+            )))
+        );
+        assert_eq!(
+            parse("config set p2.debouncer schmitt"),
+            Err(ParseError::UnknownDebouncer)
+        );
+        assert_eq!(parse("config reset"), Err(ParseError::UnknownCommand));
+    }
+
+    #[test]
+    fn values_read_back_from_how_they_show() {
+        let mut config = Config::DEFAULT;
+        for setting in Setting::all(MAX_PLAYERS) {
+            let line = std::format!("config set {setting} {}", config.get(setting));
+            assert_eq!(
+                parse(&line),
+                Ok(Some(Command::ConfigSet(setting, config.get(setting)))),
+                "{line}"
+            );
+        }
+        // Every foreperiod kind a config can hold
+        config.foreperiod = Foreperiod::CLASSIC;
+        config.practice_foreperiod = Foreperiod::Geometric {
+            min: 1_000_000,
+            step: 250_000,
+            hazard_per_mille: 150,
+            max_steps: 12,
+        };
+        config.bot = Some(ReactionDistribution::Uniform {
+            min: 150_000,
+            max: 300_000,
+        });
+        for setting in [
+            Setting::Foreperiod,
+            Setting::PracticeForeperiod,
+            Setting::Bot,
+        ] {
+            let line = std::format!("config set {setting} {}", config.get(setting));
+            assert_eq!(
+                parse(&line),
+                Ok(Some(Command::ConfigSet(setting, config.get(setting)))),
+                "{line}"
+            );
+        }
+    }
+
+    #[test]
+    fn settings_the_board_cant_take_are_refused() {
+        let set = |line| match parse(line) {
+            Ok(Some(Command::ConfigSet(setting, value))) => {
+                Config::DEFAULT.with_setting(setting, value, 2, &[25])
+            }
+            parsed => panic!("{line}: {parsed:?}"),
+        };
+        assert_eq!(
+            set("config set watchdog_starve_ms 5000").map(|c| c.watchdog_starve_ms),
+            Some(5_000)
+        );
+        // Arming the watchdog would panic on every boot, before the console could undo it
+        assert_eq!(set("config set watchdog_starve_ms 10000"), None);
+        assert_eq!(set("config set watchdog_feed_ms 0"), None);
+        assert_eq!(set("config set p2.led 10"), None);
+        assert_eq!(set("config set p1.button 25"), None);
+        // Long enough to starve the liveness check mid round
+        assert_eq!(set("config set countdown_ms 5000"), None);
+        assert_eq!(
+            set("config set format best-of 7").map(|c| c.format),
+            Some(MatchFormat::BestOf(7))
+        );
+        // A mean far past its span is a typo, not a hold anyone wants
+        assert_eq!(set("config set foreperiod exponential 1 100000000 2"), None);
+        assert_eq!(
+            set("config set bot uniform 150 300").and_then(|c| c.bot),
+            Some(ReactionDistribution::Uniform {
+                min: 150_000,
+                max: 300_000
+            })
+        );
+        assert_eq!(set("config set bot uniform 300 150"), None);
+        assert_eq!(set("config set bot normal 9000 500"), None);
+    }
+
+    #[test]
+    fn lines_end_on_cr_or_lf() {
+        let typed = b"status\r\nmode\nstaz\x08ts\r\r\n";
+        assert_eq!(
+            lines(typed),
+            [Ok("status".into()), Ok("mode".into()), Ok("stats".into())]
+        );
+        // Control bytes other than backspace are dropped, arrows leave their printable tail
+        assert_eq!(lines(b"\x1b[Ahelp\x07\r"), [Ok("[Ahelp".into())]);
+    }
+
+    #[test]
+    fn overlong_lines_are_refused_whole() {
+        let mut typed = std::vec![b'x'; MAX_LINE + 5];
+        typed.extend_from_slice(b"\rstatus\r");
+        assert_eq!(
+            lines(&typed),
+            [Err(ParseError::TooLong), Ok("status".into())]
+        );
+    }
+
+    #[test]
+    fn status_follows_the_events() {
+        let mut status = GameStatus::new(MatchFormat::default());
+        let round_won = |winner: ButtonRole, winner_score| {
+            GameEvent::RoundWon(RoundOutcome {
+                result: RoundResult {
+                    verdict: Verdict::Fastest,
+                    winner: Some(winner),
+                    response_time: Some(200_000),
+                    reaction_time: Some(200_000),
+                    release_time: None,
+                    margin: None,
+                    players: PlayerResults::from([PlayerResult::NoPress; 2]),
+                },
+                winner_score,
+                match_winner: None,
+            })
+        };
+        status.scores[0] = 3;
+        for event in [
+            GameEvent::FormatChanged {
+                format: MatchFormat::SuddenDeath,
+            },
+            GameEvent::StateChanged {
+                from: GameState::Waiting,
+                to: GameState::Playing,
+                duration: 0,
+            },
+            GameEvent::RoundStarted { index: 0 },
+            round_won(ButtonRole::PLAYER_2, 1),
+            GameEvent::MatchWon {
+                winner: ButtonRole::PLAYER_2,
+            },
+        ] {
+            status.on_event(&event);
+        }
+        assert_eq!(status.state, GameState::Playing);
+        assert_eq!(status.format, MatchFormat::SuddenDeath);
+        assert_eq!(status.round, Some(0));
+        assert_eq!(status.scores[..2], [0, 1]);
+        assert_eq!(status.last_winner, Some(ButtonRole::PLAYER_2));
+    }
+}
@@ -0,0 +1,466 @@
+use crate::Micros;
+
+/// Level change confirmed by a [`Debouncer`]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DebouncedEdge {
+    pub pressed: bool,
+    /// When the raw edge that started it happened, not when it got confirmed
+    pub at: Micros,
+}
+
+/// Turns the raw, bouncy edges of a contact into clean press and release edges
+///
+/// The driver reports every raw level change with [`Self::on_edge`] and calls
+/// [`Self::poll`] once [`Self::deadline`] is reached.
+pub trait Debouncer {
+    /// Raw level changed to `pressed` at `at`
+    fn on_edge(&mut self, pressed: bool, at: Micros) -> Option<DebouncedEdge>;
+    /// When [`Self::poll`] has to be called, `None` while nothing is pending
+    fn deadline(&self) -> Option<Micros>;
+    fn poll(&mut self, now: Micros) -> Option<DebouncedEdge>;
+    /// Raw level as last reported
+    fn raw_level(&self) -> bool;
+    /// Longest delay between a raw edge and its confirmation
+    fn latency(&self) -> Micros;
+}
+
+/// Accept the first edge right away, then ignore bounces for `lockout`
+///
+/// No added latency and the fastest taps get through, but a single glitch counts as a press.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TimeLock {
+    lockout: Micros,
+    stable: bool,
+    raw: bool,
+    raw_at: Micros,
+    locked_until: Micros,
+}
+
+impl TimeLock {
+    pub const fn new(lockout: Micros) -> Self {
+        Self {
+            lockout,
+            stable: false,
+            raw: false,
+            raw_at: 0,
+            locked_until: 0,
+        }
+    }
+
+    fn accept(&mut self, pressed: bool, at: Micros, now: Micros) -> Option<DebouncedEdge> {
+        self.stable = pressed;
+        self.locked_until = now + self.lockout;
+        Some(DebouncedEdge { pressed, at })
+    }
+}
+
+impl Debouncer for TimeLock {
+    fn on_edge(&mut self, pressed: bool, at: Micros) -> Option<DebouncedEdge> {
+        self.raw = pressed;
+        self.raw_at = at;
+        match at >= self.locked_until && pressed != self.stable {
+            true => self.accept(pressed, at, at),
+            false => None,
+        }
+    }
+
+    fn deadline(&self) -> Option<Micros> {
+        // The level settled on the other side while locked
+        (self.raw != self.stable).then_some(self.locked_until)
+    }
+
+    fn poll(&mut self, now: Micros) -> Option<DebouncedEdge> {
+        match now >= self.locked_until && self.raw != self.stable {
+            true => self.accept(self.raw, self.raw_at, now),
+            false => None,
+        }
+    }
+
+    fn raw_level(&self) -> bool {
+        self.raw
+    }
+
+    fn latency(&self) -> Micros {
+        self.lockout
+    }
+}
+
+/// Sample the level every `sample_period` and count towards it, switch once saturated
+///
+/// Rejects glitches shorter than `threshold` samples, at the cost of that much latency.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Integrator {
+    sample_period: Micros,
+    threshold: u8,
+    count: u8,
+    stable: bool,
+    raw: bool,
+    run_start: Micros,
+    next_sample: Option<Micros>,
+}
+
+impl Integrator {
+    pub const fn new(sample_period: Micros, threshold: u8) -> Self {
+        Self {
+            sample_period,
+            threshold,
+            count: 0,
+            stable: false,
+            raw: false,
+            run_start: 0,
+            next_sample: None,
+        }
+    }
+
+    fn at_rest(&self) -> bool {
+        self.count == if self.stable { self.threshold } else { 0 }
+    }
+}
+
+impl Debouncer for Integrator {
+    fn on_edge(&mut self, pressed: bool, at: Micros) -> Option<DebouncedEdge> {
+        // A run towards the other level starts from a counter at rest
+        if pressed != self.stable && self.at_rest() {
+            self.run_start = at;
+        }
+        self.raw = pressed;
+        self.next_sample.get_or_insert(at + self.sample_period);
+        None
+    }
+
+    fn deadline(&self) -> Option<Micros> {
+        self.next_sample
+    }
+
+    fn poll(&mut self, now: Micros) -> Option<DebouncedEdge> {
+        while let Some(sample_at) = self.next_sample.filter(|&at| at <= now) {
+            self.count = match self.raw {
+                true => (self.count + 1).min(self.threshold),
+                false => self.count.saturating_sub(1),
+            };
+            self.next_sample = Some(sample_at + self.sample_period);
+
+            let switched = match self.stable {
+                false => self.count == self.threshold,
+                true => self.count == 0,
+            };
+            if switched {
+                self.stable = !self.stable;
+            }
+            if self.at_rest() && self.raw == self.stable {
+                self.next_sample = None;
+            }
+            if switched {
+                return Some(DebouncedEdge {
+                    pressed: self.stable,
+                    at: self.run_start,
+                });
+            }
+        }
+        None
+    }
+
+    fn raw_level(&self) -> bool {
+        self.raw
+    }
+
+    fn latency(&self) -> Micros {
+        self.sample_period * self.threshold as Micros
+    }
+}
+
+/// Wait `settle` after an edge and keep it only if the level still matches
+///
+/// The historical strategy: steady, but taps shorter than `settle` are lost.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SleepVerify {
+    settle: Micros,
+    stable: bool,
+    raw: bool,
+    pending: Option<(bool, Micros)>,
+}
+
+impl SleepVerify {
+    pub const fn new(settle: Micros) -> Self {
+        Self {
+            settle,
+            stable: false,
+            raw: false,
+            pending: None,
+        }
+    }
+}
+
+impl Debouncer for SleepVerify {
+    fn on_edge(&mut self, pressed: bool, at: Micros) -> Option<DebouncedEdge> {
+        self.raw = pressed;
+        if self.pending.is_none() && pressed != self.stable {
+            self.pending = Some((pressed, at));
+        }
+        None
+    }
+
+    fn deadline(&self) -> Option<Micros> {
+        self.pending.map(|(_, at)| at + self.settle)
+    }
+
+    fn poll(&mut self, now: Micros) -> Option<DebouncedEdge> {
+        let (pressed, at) = self.pending.filter(|&(_, at)| now >= at + self.settle)?;
+        self.pending = None;
+        // Bounced back meanwhile, wait for the next edge
+        if self.raw != pressed {
+            return None;
+        }
+        self.stable = pressed;
+        Some(DebouncedEdge { pressed, at })
+    }
+
+    fn raw_level(&self) -> bool {
+        self.raw
+    }
+
+    fn latency(&self) -> Micros {
+        self.settle
+    }
+}
+
+/// Strategy picked per button
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DebounceStrategy {
+    TimeLock(TimeLock),
+    Integrator(Integrator),
+    SleepVerify(SleepVerify),
+}
+
+impl DebounceStrategy {
+    fn inner(&mut self) -> &mut dyn Debouncer {
+        match self {
+            DebounceStrategy::TimeLock(d) => d,
+            DebounceStrategy::Integrator(d) => d,
+            DebounceStrategy::SleepVerify(d) => d,
+        }
+    }
+
+    fn inner_ref(&self) -> &dyn Debouncer {
+        match self {
+            DebounceStrategy::TimeLock(d) => d,
+            DebounceStrategy::Integrator(d) => d,
+            DebounceStrategy::SleepVerify(d) => d,
+        }
+    }
+}
+
+impl Debouncer for DebounceStrategy {
+    fn on_edge(&mut self, pressed: bool, at: Micros) -> Option<DebouncedEdge> {
+        self.inner().on_edge(pressed, at)
+    }
+
+    fn deadline(&self) -> Option<Micros> {
+        self.inner_ref().deadline()
+    }
+
+    fn poll(&mut self, now: Micros) -> Option<DebouncedEdge> {
+        self.inner().poll(now)
+    }
+
+    fn raw_level(&self) -> bool {
+        self.inner_ref().raw_level()
+    }
+
+    fn latency(&self) -> Micros {
+        self.inner_ref().latency()
+    }
+}
+
+/// Sleep-and-verify over 50 ms, as measured on the original buttons
+impl Default for DebounceStrategy {
+    fn default() -> Self {
+        DebounceStrategy::SleepVerify(SleepVerify::new(50_000))
+    }
+}
+
+/// Which [`DebounceStrategy`] a button uses, its time given apart, e.g. by the calibration
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DebounceKind {
+    TimeLock = 0,
+    Integrator = 1,
+    /// The historical one
+    #[default]
+    SleepVerify = 2,
+}
+
+// Samples an integrator counts to switch, its period spreads them over the debounce time
+const INTEGRATOR_SAMPLES: u8 = 5;
+
+impl DebounceKind {
+    /// Every kind by the name the console gives it
+    pub const NAMED: [(&'static str, DebounceKind); 3] = [
+        ("time-lock", DebounceKind::TimeLock),
+        ("integrator", DebounceKind::Integrator),
+        ("sleep-verify", DebounceKind::SleepVerify),
+    ];
+
+    /// This kind of strategy, confirming edges within `debounce`
+    pub fn strategy(self, debounce: Micros) -> DebounceStrategy {
+        match self {
+            DebounceKind::TimeLock => DebounceStrategy::TimeLock(TimeLock::new(debounce)),
+            DebounceKind::Integrator => DebounceStrategy::Integrator(Integrator::new(
+                (debounce / INTEGRATOR_SAMPLES as Micros).max(1),
+                INTEGRATOR_SAMPLES,
+            )),
+            DebounceKind::SleepVerify => DebounceStrategy::SleepVerify(SleepVerify::new(debounce)),
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::NAMED
+            .iter()
+            .find(|(named, _)| *named == name)
+            .map(|&(_, kind)| kind)
+    }
+}
+
+impl core::fmt::Display for DebounceKind {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let (name, _) = Self::NAMED.iter().find(|(_, kind)| kind == self).unwrap();
+        f.write_str(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use std::vec::Vec;
+
+    use super::*;
+
+    // Feed raw edges in order, polling whenever a deadline passes before the next one
+    fn run(
+        debouncer: &mut impl Debouncer,
+        edges: &[(Micros, bool)],
+        end: Micros,
+    ) -> Vec<DebouncedEdge> {
+        let mut out = Vec::new();
+        let poll_until = |debouncer: &mut dyn Debouncer, until: Micros, out: &mut Vec<_>| {
+            while let Some(due) = debouncer.deadline().filter(|&due| due <= until) {
+                out.extend(debouncer.poll(due));
+            }
+        };
+        for &(at, pressed) in edges {
+            poll_until(debouncer, at, &mut out);
+            out.extend(debouncer.on_edge(pressed, at));
+        }
+        poll_until(debouncer, end, &mut out);
+        out
+    }
+
+    fn edge(pressed: bool, at: Micros) -> DebouncedEdge {
+        DebouncedEdge { pressed, at }
+    }
+
+    // Press at 1 ms bouncing for 3 ms, release at 200 ms bouncing for 2 ms
+    const BOUNCY_CLICK: [(Micros, bool); 8] = [
+        (1_000, true),
+        (1_500, false),
+        (2_500, true),
+        (4_000, false),
+        (4_200, true),
+        (200_000, false),
+        (201_000, true),
+        (202_000, false),
+    ];
+
+    // A 10 ms tap, shorter than the sleep-and-verify settle time
+    const FAST_TAP: [(Micros, bool); 2] = [(0, true), (10_000, false)];
+
+    // A 500 us spike
+    const GLITCH: [(Micros, bool); 2] = [(0, true), (500, false)];
+
+    #[test]
+    fn time_lock_takes_the_first_edge() {
+        let mut debouncer = TimeLock::new(20_000);
+        assert_eq!(
+            run(&mut debouncer, &BOUNCY_CLICK, 1_000_000),
+            [edge(true, 1_000), edge(false, 200_000)]
+        );
+        let mut debouncer = TimeLock::new(5_000);
+        assert_eq!(
+            run(&mut debouncer, &FAST_TAP, 1_000_000),
+            [edge(true, 0), edge(false, 10_000)]
+        );
+    }
+
+    #[test]
+    fn time_lock_catches_a_level_settled_while_locked() {
+        let mut debouncer = TimeLock::new(20_000);
+        // Glitch fully inside the lockout, the release is only seen once it's over
+        assert_eq!(
+            run(&mut debouncer, &GLITCH, 1_000_000),
+            [edge(true, 0), edge(false, 500)]
+        );
+        assert!(!debouncer.raw_level());
+    }
+
+    #[test]
+    fn integrator_rejects_glitches() {
+        let mut debouncer = Integrator::new(1_000, 5);
+        assert_eq!(run(&mut debouncer, &GLITCH, 1_000_000), []);
+        assert_eq!(debouncer.deadline(), None);
+        // Bounces shorter than a sample period restart the run, edges date from its last start
+        let mut debouncer = Integrator::new(1_000, 5);
+        assert_eq!(
+            run(&mut debouncer, &BOUNCY_CLICK, 1_000_000),
+            [edge(true, 2_500), edge(false, 202_000)]
+        );
+        assert_eq!(debouncer.latency(), 5_000);
+    }
+
+    #[test]
+    fn sleep_verify_loses_fast_taps() {
+        let mut debouncer = SleepVerify::new(50_000);
+        assert_eq!(
+            run(&mut debouncer, &BOUNCY_CLICK, 1_000_000),
+            [edge(true, 1_000), edge(false, 200_000)]
+        );
+        let mut debouncer = SleepVerify::new(50_000);
+        assert_eq!(run(&mut debouncer, &FAST_TAP, 1_000_000), []);
+        assert_eq!(run(&mut debouncer, &GLITCH, 1_000_000), []);
+    }
+
+    #[test]
+    fn strategy_dispatches_to_its_debouncer() {
+        let mut strategy = DebounceStrategy::TimeLock(TimeLock::new(5_000));
+        assert_eq!(
+            run(&mut strategy, &FAST_TAP, 1_000_000),
+            [edge(true, 0), edge(false, 10_000)]
+        );
+        assert_eq!(DebounceStrategy::default().latency(), 50_000);
+    }
+
+    #[test]
+    fn every_kind_confirms_within_its_time() {
+        for (name, kind) in DebounceKind::NAMED {
+            let strategy = kind.strategy(5_000);
+            assert_eq!(strategy.latency(), 5_000, "{name}");
+            assert_eq!(DebounceKind::parse(name), Some(kind));
+            assert_eq!(std::format!("{kind}"), name);
+        }
+        assert_eq!(
+            DebounceKind::Integrator.strategy(5_000),
+            DebounceStrategy::Integrator(Integrator::new(1_000, 5))
+        );
+        // No time at all still samples
+        let mut strategy = DebounceKind::Integrator.strategy(0);
+        assert_eq!(
+            run(&mut strategy, &BOUNCY_CLICK[..1], 1_000_000),
+            [edge(true, 1_000)]
+        );
+        assert_eq!(DebounceKind::parse("sleep"), None);
+    }
+}
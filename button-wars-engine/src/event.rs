@@ -0,0 +1,42 @@
+use crate::{ButtonRole, GameState, MatchFormat, Micros, PracticeSummary, RoundOutcome, Trial};
+
+/// Something that happened in the game, broadcast to every interested task
+///
+/// LEDs, logs, a display or telemetry each react to the same stream without
+/// knowing about each other.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+// Round results carry every player, there's no allocator to box them
+#[allow(clippy::large_enum_variant)]
+pub enum GameEvent {
+    /// Accepted transition, after `duration` spent in `from`
+    StateChanged {
+        from: GameState,
+        to: GameState,
+        duration: Micros,
+    },
+    /// Format of the next matches picked from the menu or the console
+    FormatChanged { format: MatchFormat },
+    /// Countdown of round `index` began
+    RoundStarted { index: usize },
+    /// LEDs went off, presses from now on race
+    Go { at: Micros },
+    /// Debounced press edge, before or after GO
+    ButtonPressed { player: ButtonRole, at: Micros },
+    /// A round counted towards the match, nobody won it when `result.winner` is `None`
+    RoundWon(RoundOutcome),
+    /// The match format decided the match
+    MatchWon { winner: ButtonRole },
+    /// Practice trial `index` ended, `personal_best` as it stood before the session
+    TrialDone {
+        player: ButtonRole,
+        index: usize,
+        trial: Trial,
+        personal_best: Option<Micros>,
+    },
+    /// Practice session over, `new_best` when it beat the personal best
+    PracticeDone {
+        summary: PracticeSummary,
+        new_best: bool,
+    },
+}
@@ -0,0 +1,263 @@
+use crate::{Micros, RandomSource};
+
+/// How long the LEDs stay on before GO
+///
+/// With a uniform wait GO gets more certain the longer it hasn't come, so players can
+/// anticipate the end of the range. The exponential and geometric ones are non-aging:
+/// GO is as likely in the next instant whatever the time already waited.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Foreperiod {
+    /// Anywhere in `min..=max`, all equally likely
+    Uniform { min: Micros, max: Micros },
+    /// `min` plus an exponential wait of mean `mean`, truncated to end before `max`
+    Exponential {
+        min: Micros,
+        mean: Micros,
+        max: Micros,
+    },
+    /// `min` plus whole `step`s, GO coming at each one with `hazard_per_mille`,
+    /// and at the latest after `max_steps`
+    Geometric {
+        min: Micros,
+        step: Micros,
+        hazard_per_mille: u16,
+        max_steps: u16,
+    },
+    /// One of these, all equally likely
+    Fixed(&'static [Micros]),
+}
+
+// ln(2) in Q32 fixed point
+const LN_2_Q32: u64 = 0xB172_17F8;
+
+impl Foreperiod {
+    /// The historical 2 to 5 s
+    pub const CLASSIC: Self = Self::Uniform {
+        min: 2_000_000,
+        max: 5_000_000,
+    };
+    /// Around 3 s like the classic one, without the certainty building up
+    pub const NON_AGING: Self = Self::Exponential {
+        min: 1_500_000,
+        mean: 1_500_000,
+        max: 8_000_000,
+    };
+
+    /// Longest mean of an exponential hold, in spans of its range. Past it the hold is all
+    /// but uniform, a mistyped mean more likely than a wanted one
+    pub const MAX_MEAN_SPANS: Micros = 4;
+
+    /// Whether the distribution can be drawn from as meant: a range doesn't end before it
+    /// starts, an exponential mean is positive and isn't far past the span it's truncated to,
+    /// and a geometric hazard is a probability over steps that take time
+    pub const fn is_valid(&self) -> bool {
+        match *self {
+            Self::Uniform { min, max } => min <= max,
+            Self::Exponential { min, mean, max } => {
+                min <= max && mean > 0 && mean <= (max - min).saturating_mul(Self::MAX_MEAN_SPANS)
+            }
+            Self::Geometric {
+                step,
+                hazard_per_mille,
+                max_steps,
+                ..
+            } => hazard_per_mille > 0 && hazard_per_mille <= 1000 && (step > 0 || max_steps == 0),
+            Self::Fixed(_) => true,
+        }
+    }
+
+    /// Longest hold it can draw
+    pub const fn longest(&self) -> Micros {
+        match *self {
+            Self::Uniform { min, max } | Self::Exponential { min, max, .. } => {
+                if min > max {
+                    min
+                } else {
+                    max
+                }
+            }
+            Self::Geometric {
+                min,
+                step,
+                max_steps,
+                ..
+            } => min.saturating_add(step.saturating_mul(max_steps as Micros)),
+            Self::Fixed(choices) => {
+                let mut longest = 0;
+                let mut i = 0;
+                while i < choices.len() {
+                    if choices[i] > longest {
+                        longest = choices[i];
+                    }
+                    i += 1;
+                }
+                longest
+            }
+        }
+    }
+
+    pub fn sample(&self, rng: &mut impl RandomSource) -> Micros {
+        match *self {
+            Self::Uniform { min, max } => rng.range_inclusive(min, max),
+            Self::Exponential { min, max, .. } if max <= min => min,
+            // Folding the exponential into the span draws the truncated one in a single go:
+            // its density at x sums up e^(-(x + k span) / mean) for every k, which is
+            // e^(-x / mean) scaled, however far the mean is past the span
+            Self::Exponential { min, mean, max } => {
+                let wait = (neg_ln_q32(rng.next_u64().max(1)) as u128 * mean as u128) >> 32;
+                min + (wait % (max - min) as u128) as Micros
+            }
+            Self::Geometric {
+                min,
+                step,
+                hazard_per_mille,
+                max_steps,
+            } => {
+                let steps = (0..max_steps)
+                    .find(|_| rng.below(1000) < hazard_per_mille as u64)
+                    .unwrap_or(max_steps);
+                min + steps as Micros * step
+            }
+            Self::Fixed([]) => 0,
+            Self::Fixed(choices) => choices[rng.below(choices.len() as u64) as usize],
+        }
+    }
+}
+
+// -ln(u / 2^64) in Q32 fixed point for u > 0, the log2 fraction comes from repeated squaring
+fn neg_ln_q32(u: u64) -> u64 {
+    let shift = u.leading_zeros();
+    // Mantissa in [1, 2), as Q63
+    let mut mantissa = (u << shift) as u128;
+    let mut fraction = 0u64;
+    for _ in 0..32 {
+        mantissa = (mantissa * mantissa) >> 63;
+        fraction <<= 1;
+        if mantissa >= 1 << 64 {
+            fraction |= 1;
+            mantissa >>= 1;
+        }
+    }
+    // log2(u / 2^64) is fraction - (shift + 1)
+    let neg_log2 = ((shift as u64 + 1) << 32) - fraction;
+    ((neg_log2 as u128 * LN_2_Q32 as u128) >> 32) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::Xoshiro256;
+
+    const DRAWS: usize = 40_000;
+
+    // Chance per mille of GO within each `bin` wide slice after `min`, given it hasn't come yet
+    fn hazard(foreperiod: Foreperiod, min: Micros, bin: Micros, bins: usize) -> Vec<u64> {
+        let mut rng = Xoshiro256::from_seed(3);
+        let mut counts = std::vec![0u64; bins];
+        for _ in 0..DRAWS {
+            let at = foreperiod.sample(&mut rng);
+            assert!(at >= min);
+            counts[(((at - min) / bin) as usize).min(bins - 1)] += 1;
+        }
+        let mut survivors = DRAWS as u64;
+        counts
+            .iter()
+            .map(|&count| {
+                let hazard = count * 1000 / survivors.max(1);
+                survivors -= count;
+                hazard
+            })
+            .collect()
+    }
+
+    #[test]
+    fn natural_log_in_fixed_point() {
+        assert_eq!(neg_ln_q32(1 << 63), LN_2_Q32);
+        assert_eq!(neg_ln_q32(1 << 62), 2 * LN_2_Q32);
+        assert_eq!(neg_ln_q32(u64::MAX), 0);
+        // -ln(0.75) = 0.287682
+        let ln = neg_ln_q32(3 << 62);
+        assert!(ln.abs_diff(1_235_585_000) < 10_000, "{ln}");
+    }
+
+    #[test]
+    fn uniform_hazard_builds_up() {
+        // 500 ms bins over the classic 2 to 5 s
+        let hazard = hazard(Foreperiod::CLASSIC, 2_000_000, 500_000, 6);
+        assert!(
+            hazard.windows(2).all(|pair| pair[0] < pair[1]),
+            "{hazard:?}"
+        );
+        // By the last bin GO is certain
+        assert!(hazard[5] > 990, "{hazard:?}");
+    }
+
+    #[test]
+    fn exponential_hazard_stays_flat() {
+        // 1 - e^(-0.5 / 1.5) = 283 per mille for every 500 ms bin
+        let hazard = hazard(Foreperiod::NON_AGING, 1_500_000, 500_000, 8);
+        for h in &hazard[..6] {
+            assert!(h.abs_diff(283) < 30, "{hazard:?}");
+        }
+        let mut rng = Xoshiro256::from_seed(9);
+        assert!((0..DRAWS).all(|_| Foreperiod::NON_AGING.sample(&mut rng) <= 8_000_000));
+    }
+
+    #[test]
+    fn exponential_past_its_span_draws_at_once() {
+        // Mean of a day over a 1 s span, nearly every draw used to land past the max
+        let wide = Foreperiod::Exponential {
+            min: 1_000_000,
+            mean: 100_000_000_000,
+            max: 2_000_000,
+        };
+        assert!(!wide.is_valid());
+        let mut rng = Xoshiro256::from_seed(4);
+        let mut halves = [0usize; 2];
+        for _ in 0..DRAWS {
+            let at = wide.sample(&mut rng);
+            assert!((1_000_000..2_000_000).contains(&at), "{at}");
+            halves[(at >= 1_500_000) as usize] += 1;
+        }
+        // All but uniform that far past the span
+        assert!(halves[0].abs_diff(halves[1]) < DRAWS / 20, "{halves:?}");
+        assert!(Foreperiod::NON_AGING.is_valid());
+    }
+
+    #[test]
+    fn geometric_hazard_is_the_step_chance() {
+        let geometric = Foreperiod::Geometric {
+            min: 1_000_000,
+            step: 250_000,
+            hazard_per_mille: 150,
+            max_steps: 12,
+        };
+        let hazard = hazard(geometric, 1_000_000, 250_000, 13);
+        for h in &hazard[..8] {
+            assert!(h.abs_diff(150) < 25, "{hazard:?}");
+        }
+        // Whoever is left goes at the last step
+        assert_eq!(hazard[12], 1000);
+    }
+
+    #[test]
+    fn fixed_set_draws_each_value() {
+        let fixed = Foreperiod::Fixed(&[2_000_000, 3_000_000, 4_500_000]);
+        let mut rng = Xoshiro256::from_seed(5);
+        let mut seen = [0; 3];
+        for _ in 0..3_000 {
+            match fixed.sample(&mut rng) {
+                2_000_000 => seen[0] += 1,
+                3_000_000 => seen[1] += 1,
+                4_500_000 => seen[2] += 1,
+                other => panic!("{other} not in the set"),
+            }
+        }
+        assert!(seen.iter().all(|&n| (900..1_100).contains(&n)), "{seen:?}");
+        assert_eq!(Foreperiod::Fixed(&[]).sample(&mut rng), 0);
+    }
+}
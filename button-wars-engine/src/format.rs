@@ -0,0 +1,139 @@
+use crate::Foreperiod;
+
+/// How a match is won, picked from the Waiting state before each game
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum MatchFormat {
+    /// First to win a majority of this many rounds
+    BestOf(u8),
+    /// First to win this many rounds, however many it takes
+    FirstTo(u8),
+    /// First to win this many rounds with a lead of two
+    WinByTwo(u8),
+    /// Exactly this many rounds, the lowest total reaction time wins
+    TotalTime(u8),
+    /// The first round won decides the match
+    SuddenDeath,
+}
+
+impl MatchFormat {
+    /// Formats cycled through on the device, the first one is the default
+    pub const PRESETS: [MatchFormat; 6] = [
+        MatchFormat::BestOf(5),
+        MatchFormat::BestOf(3),
+        MatchFormat::FirstTo(5),
+        MatchFormat::WinByTwo(3),
+        MatchFormat::TotalTime(5),
+        MatchFormat::SuddenDeath,
+    ];
+
+    /// Position in [`Self::PRESETS`], `None` for a custom format
+    pub fn preset_index(self) -> Option<usize> {
+        Self::PRESETS.iter().position(|&f| f == self)
+    }
+
+    /// Next preset, wrapping around, a custom format goes back to the default
+    pub fn next_preset(self) -> Self {
+        let next = self
+            .preset_index()
+            .map_or(0, |i| (i + 1) % Self::PRESETS.len());
+        Self::PRESETS[next]
+    }
+
+    /// Foreperiod of the mode, overriding the board's, `None` to keep it
+    ///
+    /// Sudden death hangs on a single GO, a geometric hold keeps it unguessable to the end.
+    /// Total time adds up every round, a fixed set of holds keeps totals comparable whatever
+    /// the board uses.
+    pub const fn foreperiod(self) -> Option<Foreperiod> {
+        match self {
+            MatchFormat::SuddenDeath => Some(Foreperiod::Geometric {
+                min: 1_500_000,
+                step: 250_000,
+                hazard_per_mille: 100,
+                max_steps: 26,
+            }),
+            MatchFormat::TotalTime(_) => Some(Foreperiod::Fixed(&[
+                2_000_000, 2_500_000, 3_000_000, 3_500_000, 4_000_000,
+            ])),
+            MatchFormat::BestOf(_) | MatchFormat::FirstTo(_) | MatchFormat::WinByTwo(_) => None,
+        }
+    }
+
+    /// Round wins needed to take the match, `None` when decided on time instead
+    pub const fn wins_needed(self) -> Option<usize> {
+        match self {
+            MatchFormat::BestOf(rounds) => Some((rounds as usize).div_ceil(2)),
+            MatchFormat::FirstTo(wins) | MatchFormat::WinByTwo(wins) => Some(wins as usize),
+            MatchFormat::TotalTime(_) => None,
+            MatchFormat::SuddenDeath => Some(1),
+        }
+    }
+
+    /// Rounds played at most, `None` when the match goes on until someone wins
+    pub const fn round_limit(self) -> Option<usize> {
+        match self {
+            MatchFormat::BestOf(rounds) | MatchFormat::TotalTime(rounds) => Some(rounds as usize),
+            MatchFormat::FirstTo(_) | MatchFormat::WinByTwo(_) | MatchFormat::SuddenDeath => None,
+        }
+    }
+
+    /// Whether `score` against `opponent_score` takes the match on round wins
+    pub const fn is_won(self, score: usize, opponent_score: usize) -> bool {
+        let Some(wins) = self.wins_needed() else {
+            return false;
+        };
+        match self {
+            MatchFormat::WinByTwo(_) => score >= wins && score >= opponent_score + 2,
+            _ => score >= wins,
+        }
+    }
+}
+
+impl Default for MatchFormat {
+    fn default() -> Self {
+        Self::PRESETS[0]
+    }
+}
+
+/// As typed in the console, e.g. `best-of 5`
+impl core::fmt::Display for MatchFormat {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            MatchFormat::BestOf(n) => write!(f, "best-of {n}"),
+            MatchFormat::FirstTo(n) => write!(f, "first-to {n}"),
+            MatchFormat::WinByTwo(n) => write!(f, "win-by-two {n}"),
+            MatchFormat::TotalTime(n) => write!(f, "total-time {n}"),
+            MatchFormat::SuddenDeath => write!(f, "sudden-death"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_cycle_back_to_default() {
+        let mut format = MatchFormat::default();
+        for _ in 0..MatchFormat::PRESETS.len() {
+            format = format.next_preset();
+        }
+        assert_eq!(format, MatchFormat::BestOf(5));
+        assert_eq!(
+            MatchFormat::FirstTo(9).next_preset(),
+            MatchFormat::BestOf(5)
+        );
+    }
+
+    #[test]
+    fn win_conditions() {
+        assert!(MatchFormat::BestOf(5).is_won(3, 2));
+        assert!(!MatchFormat::BestOf(5).is_won(2, 0));
+        assert!(MatchFormat::FirstTo(5).is_won(5, 4));
+        assert!(!MatchFormat::WinByTwo(3).is_won(3, 2));
+        assert!(MatchFormat::WinByTwo(3).is_won(5, 3));
+        assert!(MatchFormat::SuddenDeath.is_won(1, 0));
+        assert!(!MatchFormat::TotalTime(5).is_won(5, 0));
+    }
+}
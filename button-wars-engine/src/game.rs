@@ -0,0 +1,538 @@
+use heapless::{Entry, FnvIndexMap, Vec};
+
+use crate::{
+    ButtonRole, MatchFormat, MatchStats, Micros, RandomSource, Round, RoundResult, RoundRules,
+    TiePolicy, Verdict, MAX_PLAYERS,
+};
+
+/// Most rounds kept in a match history, open-ended formats stop there
+pub const MAX_ROUNDS: usize = 32;
+
+/// Decision emitted after a round result was accounted for
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RoundOutcome {
+    pub result: RoundResult,
+    /// Score of the round winner including this round, 0 for a void round
+    pub winner_score: usize,
+    /// Set once the match format decided the match
+    pub match_winner: Option<ButtonRole>,
+}
+
+/// Scores and round history of a match played in a [`MatchFormat`]
+pub struct Match {
+    format: MatchFormat,
+    rules: RoundRules,
+    players: usize,
+    players_scores: FnvIndexMap<ButtonRole, usize, MAX_PLAYERS>,
+    round_winner_times: Vec<RoundResult, MAX_ROUNDS>,
+    /// Indexed by [`ButtonRole::index`], players who gave up
+    forfeited: [bool; MAX_PLAYERS],
+}
+
+impl Match {
+    /// A duel, see [`Self::set_players`] for more players
+    pub fn new(format: MatchFormat, rules: RoundRules) -> Self {
+        let mut game = Self {
+            format,
+            rules,
+            players: 2,
+            players_scores: FnvIndexMap::new(),
+            round_winner_times: Vec::new(),
+            forfeited: [false; MAX_PLAYERS],
+        };
+        game.reset();
+        game
+    }
+
+    /// Clear scores and history before the next game
+    pub fn reset(&mut self) {
+        self.players_scores.clear();
+        for role in ButtonRole::first(self.players) {
+            self.players_scores.insert(role, 0).unwrap();
+        }
+        self.round_winner_times.clear();
+        self.forfeited = [false; MAX_PLAYERS];
+    }
+
+    pub fn players(&self) -> usize {
+        self.players
+    }
+
+    /// Play with the first `players` players from now on, from 2 up to [`MAX_PLAYERS`]
+    ///
+    /// Scores and history are cleared.
+    pub fn set_players(&mut self, players: usize) {
+        self.players = players.clamp(2, MAX_PLAYERS);
+        self.reset();
+    }
+
+    pub fn format(&self) -> MatchFormat {
+        self.format
+    }
+
+    /// Switch format for the next game, scores and history are cleared
+    pub fn set_format(&mut self, format: MatchFormat) {
+        self.format = format;
+        self.reset();
+    }
+
+    /// Start the hold phase of the next round, or `None` once the match is over
+    pub fn start_round(&self, rng: &mut impl RandomSource) -> Option<Round> {
+        if self.is_over() {
+            return None;
+        }
+        let mut round = Round::new(self.current_round(), self.players, self.rules(), rng);
+        for player in ButtonRole::first(self.players).filter(|p| self.forfeited[p.index()]) {
+            round.sit_out(player);
+        }
+        Some(round)
+    }
+
+    /// Rules of the next rounds, with the foreperiod of the format if it has one
+    pub fn rules(&self) -> RoundRules {
+        RoundRules {
+            foreperiod: self.format.foreperiod().unwrap_or(self.rules.foreperiod),
+            ..self.rules
+        }
+    }
+
+    /// Index of the next round to be played
+    pub fn current_round(&self) -> usize {
+        self.round_winner_times.len()
+    }
+
+    /// Account a finished round and check for the match winner
+    ///
+    /// A tie settled by [`TiePolicy::Replay`] leaves the match untouched so the round is replayed.
+    pub fn finish_round(&mut self, result: RoundResult) -> RoundOutcome {
+        if result.verdict == Verdict::Tie(TiePolicy::Replay) {
+            return RoundOutcome {
+                result,
+                winner_score: 0,
+                match_winner: self.winner(),
+            };
+        }
+        // Ignore extra rounds, the match is already decided at this point
+        let _ = self.round_winner_times.push(result);
+
+        let mut winner_score = 0;
+        if let Some(winner) = result.winner.filter(|w| !self.forfeited[w.index()]) {
+            if let Entry::Occupied(mut o) = self.players_scores.entry(winner) {
+                *o.get_mut() += 1;
+                winner_score = *o.get();
+            }
+        }
+
+        RoundOutcome {
+            result,
+            winner_score,
+            match_winner: self.winner(),
+        }
+    }
+
+    /// `player` gives up, they sit out the next rounds and the last one left takes the match
+    ///
+    /// Returns the match winner once decided.
+    pub fn forfeit(&mut self, player: ButtonRole) -> Option<ButtonRole> {
+        if let Some(forfeited) = self.forfeited.get_mut(player.index()) {
+            *forfeited = true;
+        }
+        self.winner()
+    }
+
+    // Players who haven't forfeited
+    fn contenders(&self) -> impl Iterator<Item = ButtonRole> + '_ {
+        ButtonRole::first(self.players).filter(|p| !self.forfeited[p.index()])
+    }
+
+    /// Whether no more rounds will be played
+    pub fn is_over(&self) -> bool {
+        let limit = self.format.round_limit().unwrap_or(MAX_ROUNDS);
+        self.winner().is_some() || self.current_round() >= limit.min(MAX_ROUNDS)
+    }
+
+    /// Player who won the match under its format, if decided
+    ///
+    /// Once the round limit is reached without a decision, the sole leader wins.
+    pub fn winner(&self) -> Option<ButtonRole> {
+        let mut contenders = self.contenders();
+        if let (Some(last), None) = (contenders.next(), contenders.next()) {
+            return Some(last);
+        }
+        if let MatchFormat::TotalTime(rounds) = self.format {
+            return (self.current_round() >= rounds as usize)
+                .then(|| self.sole_best(|p| self.total_time(p), |a, b| a < b))
+                .flatten();
+        }
+        let by_score = self
+            .contenders()
+            .find(|&p| self.format.is_won(self.score(p), self.best_other_score(p)));
+        let limit = self
+            .format
+            .round_limit()
+            .unwrap_or(MAX_ROUNDS)
+            .min(MAX_ROUNDS);
+        match by_score {
+            Some(winner) => Some(winner),
+            None if self.current_round() >= limit => {
+                self.sole_best(|p| self.score(p), |a, b| a > b)
+            }
+            None => None,
+        }
+    }
+
+    // Highest score among the other players, what a lead is measured against
+    fn best_other_score(&self, player: ButtonRole) -> usize {
+        self.scores()
+            .filter(|&(p, _)| p != player)
+            .map(|(_, s)| s)
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub fn score(&self, player: ButtonRole) -> usize {
+        self.players_scores.get(&player).copied().unwrap_or(0)
+    }
+
+    pub fn scores(&self) -> impl Iterator<Item = (ButtonRole, usize)> + '_ {
+        self.players_scores.iter().map(|(p, s)| (*p, *s))
+    }
+
+    /// Sum of the player's scored times, a round without one costs the full timeout
+    pub fn total_time(&self, player: ButtonRole) -> Micros {
+        self.round_winner_times
+            .iter()
+            .map(|r| {
+                r.player(player)
+                    .score(&self.rules)
+                    .unwrap_or(self.rules.timeout)
+            })
+            .sum()
+    }
+
+    pub fn rounds(&self) -> &[RoundResult] {
+        &self.round_winner_times
+    }
+
+    /// Stats of every player over the rounds played so far
+    pub fn stats(&self) -> MatchStats {
+        MatchStats::from_rounds(self.players, &self.round_winner_times)
+    }
+
+    // Contender strictly ahead of every other one on `value`, `None` when shared
+    fn sole_best<T>(
+        &self,
+        value: impl Fn(ButtonRole) -> T,
+        better: impl Fn(&T, &T) -> bool,
+    ) -> Option<ButtonRole> {
+        let mut players = self.contenders();
+        let first = players.next()?;
+        let (mut best, mut best_value, mut shared) = (first, value(first), false);
+        for player in players {
+            let v = value(player);
+            if better(&v, &best_value) {
+                (best, best_value, shared) = (player, v, false);
+            } else if !better(&best_value, &v) {
+                shared = true;
+            }
+        }
+        (!shared).then_some(best)
+    }
+}
+
+impl Default for Match {
+    fn default() -> Self {
+        Self::new(MatchFormat::default(), RoundRules::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FalseStartRule, Foreperiod, Micros};
+
+    struct Heads;
+
+    impl RandomSource for Heads {
+        fn next_u64(&mut self) -> u64 {
+            0
+        }
+    }
+
+    // Times are small abstract units here, far below any plausible reaction
+    fn rules() -> RoundRules {
+        RoundRules {
+            min_reaction: 0,
+            ..Default::default()
+        }
+    }
+
+    fn play(game: &mut Match, winner: ButtonRole, response_time: Micros) -> RoundOutcome {
+        let mut round = game.start_round(&mut Heads).unwrap();
+        round.go(1_000);
+        round.on_press(winner, 1_000 + response_time);
+        game.finish_round(round.poll(Micros::MAX).unwrap())
+    }
+
+    #[test]
+    fn forfeit_hands_the_duel_over() {
+        let mut game = Match::new(MatchFormat::default(), rules());
+        play(&mut game, ButtonRole::PLAYER_2, 100);
+        play(&mut game, ButtonRole::PLAYER_2, 100);
+        assert_eq!(
+            game.forfeit(ButtonRole::PLAYER_2),
+            Some(ButtonRole::PLAYER_1)
+        );
+        assert!(game.is_over());
+        assert_eq!(game.winner(), Some(ButtonRole::PLAYER_1));
+        game.reset();
+        assert_eq!(game.winner(), None);
+    }
+
+    #[test]
+    fn forfeit_leaves_the_others_playing() {
+        let mut game = Match::new(MatchFormat::FirstTo(2), rules());
+        game.set_players(3);
+        let p3 = ButtonRole::ALL[2];
+        play(&mut game, ButtonRole::PLAYER_1, 100);
+        assert_eq!(game.forfeit(ButtonRole::PLAYER_1), None);
+        assert!(!game.is_over());
+        // The leader gave up, their presses no longer count and the round goes to player 3
+        let mut round = game.start_round(&mut Heads).unwrap();
+        round.go(1_000);
+        assert_eq!(round.on_press(ButtonRole::PLAYER_1, 1_100), None);
+        round.on_press(p3, 1_200);
+        let outcome = game.finish_round(round.poll(Micros::MAX).unwrap());
+        assert_eq!(outcome.result.winner, Some(p3));
+        assert_eq!(game.score(ButtonRole::PLAYER_1), 1);
+        assert_eq!(game.forfeit(ButtonRole::PLAYER_2), Some(p3));
+    }
+
+    #[test]
+    fn formats_can_bring_their_own_foreperiod() {
+        let board = Foreperiod::CLASSIC;
+        let mut game = Match::new(
+            MatchFormat::BestOf(3),
+            RoundRules {
+                foreperiod: board,
+                ..rules()
+            },
+        );
+        assert_eq!(
+            game.start_round(&mut Heads).unwrap().rules().foreperiod,
+            board
+        );
+        for format in [MatchFormat::SuddenDeath, MatchFormat::TotalTime(5)] {
+            game.set_format(format);
+            let foreperiod = game.start_round(&mut Heads).unwrap().rules().foreperiod;
+            assert_eq!(Some(foreperiod), format.foreperiod());
+            assert_ne!(foreperiod, board);
+        }
+    }
+
+    #[test]
+    fn best_of_five_needs_three_wins() {
+        let mut game = Match::new(MatchFormat::default(), rules());
+        assert_eq!(game.format().wins_needed(), Some(3));
+
+        assert_eq!(
+            play(&mut game, ButtonRole::PLAYER_1, 200).match_winner,
+            None
+        );
+        assert_eq!(
+            play(&mut game, ButtonRole::PLAYER_2, 300).match_winner,
+            None
+        );
+        assert_eq!(
+            play(&mut game, ButtonRole::PLAYER_1, 250).match_winner,
+            None
+        );
+        let outcome = play(&mut game, ButtonRole::PLAYER_1, 220);
+        assert_eq!(outcome.winner_score, 3);
+        assert_eq!(outcome.match_winner, Some(ButtonRole::PLAYER_1));
+        assert_eq!(game.score(ButtonRole::PLAYER_2), 1);
+        assert_eq!(game.current_round(), 4);
+    }
+
+    #[test]
+    fn no_round_after_the_last_one() {
+        let mut game = Match::new(MatchFormat::BestOf(1), rules());
+        let mut round = game.start_round(&mut Heads).unwrap();
+        round.go(0);
+        round.on_press(ButtonRole::PLAYER_2, 10);
+        game.finish_round(round.poll(Micros::MAX).unwrap());
+        assert!(game.start_round(&mut Heads).is_none());
+    }
+
+    #[test]
+    fn replayed_tie_does_not_count_as_a_round() {
+        let mut game = Match::new(MatchFormat::default(), rules());
+        let mut round = game.start_round(&mut Heads).unwrap();
+        round.go(0);
+        round.on_press(ButtonRole::PLAYER_1, 150_000);
+        round.on_press(ButtonRole::PLAYER_2, 150_200);
+        let outcome = game.finish_round(round.result().unwrap());
+        assert_eq!(outcome.result.verdict, Verdict::Tie(TiePolicy::Replay));
+        assert_eq!(game.current_round(), 0);
+        assert_eq!(game.score(ButtonRole::PLAYER_1), 0);
+    }
+
+    #[test]
+    fn void_round_scores_nobody() {
+        let mut game = Match::new(
+            MatchFormat::default(),
+            RoundRules {
+                false_start: FalseStartRule::LoseRound,
+                ..rules()
+            },
+        );
+        let mut round = game.start_round(&mut Heads).unwrap();
+        round.on_press(ButtonRole::PLAYER_1, 10);
+        round.on_press(ButtonRole::PLAYER_2, 20);
+        let outcome = game.finish_round(round.result().unwrap());
+        assert_eq!(outcome.winner_score, 0);
+        assert_eq!(game.score(ButtonRole::PLAYER_1), 0);
+        assert_eq!(game.score(ButtonRole::PLAYER_2), 0);
+        assert_eq!(game.current_round(), 1);
+    }
+
+    #[test]
+    fn reset_clears_scores_and_rounds() {
+        let mut game = Match::new(MatchFormat::default(), rules());
+        play(&mut game, ButtonRole::PLAYER_2, 180);
+        game.reset();
+        assert_eq!(game.score(ButtonRole::PLAYER_2), 0);
+        assert!(game.rounds().is_empty());
+        assert_eq!(game.winner(), None);
+    }
+
+    fn race(game: &mut Match, p1_time: Micros, p2_time: Micros) -> RoundOutcome {
+        let mut round = game.start_round(&mut Heads).unwrap();
+        round.go(0);
+        round.on_press(ButtonRole::PLAYER_1, p1_time);
+        round.on_press(ButtonRole::PLAYER_2, p2_time);
+        game.finish_round(round.poll(Micros::MAX).unwrap())
+    }
+
+    #[test]
+    fn win_by_two_goes_past_the_target() {
+        let mut game = Match::new(MatchFormat::WinByTwo(3), rules());
+        for winner in [ButtonRole::PLAYER_1, ButtonRole::PLAYER_2].repeat(2) {
+            play(&mut game, winner, 200);
+        }
+        assert_eq!(
+            play(&mut game, ButtonRole::PLAYER_1, 200).match_winner,
+            None
+        );
+        assert_eq!(
+            play(&mut game, ButtonRole::PLAYER_2, 200).match_winner,
+            None
+        );
+        assert_eq!(
+            play(&mut game, ButtonRole::PLAYER_2, 200).match_winner,
+            None
+        );
+        let outcome = play(&mut game, ButtonRole::PLAYER_2, 200);
+        assert_eq!(outcome.match_winner, Some(ButtonRole::PLAYER_2));
+        assert_eq!(game.current_round(), 8);
+    }
+
+    #[test]
+    fn total_time_plays_every_round_and_sums_times() {
+        let mut game = Match::new(MatchFormat::TotalTime(3), rules());
+        // Player 1 wins two rounds but loses big on the last one
+        race(&mut game, 200_000, 210_000);
+        race(&mut game, 200_000, 210_000);
+        assert_eq!(
+            race(&mut game, 400_000, 250_000).match_winner,
+            Some(ButtonRole::PLAYER_2)
+        );
+        assert_eq!(game.total_time(ButtonRole::PLAYER_1), 800_000);
+        assert_eq!(game.total_time(ButtonRole::PLAYER_2), 670_000);
+        assert_eq!(game.score(ButtonRole::PLAYER_1), 2);
+        assert!(game.start_round(&mut Heads).is_none());
+    }
+
+    #[test]
+    fn missed_round_costs_the_timeout_in_total_time() {
+        let mut game = Match::new(MatchFormat::TotalTime(1), rules());
+        play(&mut game, ButtonRole::PLAYER_1, 500_000);
+        assert_eq!(game.total_time(ButtonRole::PLAYER_2), 3_000_000);
+        assert_eq!(game.winner(), Some(ButtonRole::PLAYER_1));
+    }
+
+    #[test]
+    fn sudden_death_skips_void_rounds() {
+        let mut game = Match::new(MatchFormat::SuddenDeath, rules());
+        let mut round = game.start_round(&mut Heads).unwrap();
+        round.go(0);
+        assert_eq!(
+            game.finish_round(round.poll(Micros::MAX).unwrap())
+                .match_winner,
+            None
+        );
+        let outcome = play(&mut game, ButtonRole::PLAYER_2, 300);
+        assert_eq!(outcome.match_winner, Some(ButtonRole::PLAYER_2));
+    }
+
+    #[test]
+    fn best_of_falls_back_to_the_leader_after_the_last_round() {
+        let mut game = Match::new(MatchFormat::BestOf(3), rules());
+        play(&mut game, ButtonRole::PLAYER_1, 200);
+        for _ in 0..2 {
+            let mut round = game.start_round(&mut Heads).unwrap();
+            round.go(0);
+            game.finish_round(round.poll(Micros::MAX).unwrap());
+        }
+        assert!(game.is_over());
+        assert_eq!(game.winner(), Some(ButtonRole::PLAYER_1));
+    }
+
+    #[test]
+    fn open_ended_match_stops_at_max_rounds() {
+        let mut game = Match::new(MatchFormat::WinByTwo(1), rules());
+        for i in 0..MAX_ROUNDS {
+            let winner = ButtonRole::ALL[i % 2];
+            play(&mut game, winner, 200);
+        }
+        assert!(game.is_over());
+        assert_eq!(game.winner(), None);
+        assert!(game.start_round(&mut Heads).is_none());
+    }
+
+    #[test]
+    fn changing_format_resets_the_match() {
+        let mut game = Match::new(MatchFormat::default(), rules());
+        play(&mut game, ButtonRole::PLAYER_1, 200);
+        game.set_format(MatchFormat::SuddenDeath);
+        assert_eq!(game.format(), MatchFormat::SuddenDeath);
+        assert!(game.rounds().is_empty());
+    }
+
+    #[test]
+    fn more_players_each_race_the_best_of_the_others() {
+        let mut game = Match::new(MatchFormat::WinByTwo(2), rules());
+        game.set_players(3);
+        assert_eq!(game.scores().count(), 3);
+        let [p1, p2, p3, ..] = ButtonRole::ALL;
+        play(&mut game, p1, 200);
+        play(&mut game, p2, 200);
+        // Two wins but only one ahead of player 2
+        assert_eq!(play(&mut game, p1, 200).match_winner, None);
+        assert_eq!(play(&mut game, p3, 200).match_winner, None);
+        assert_eq!(play(&mut game, p1, 200).match_winner, Some(p1));
+        assert_eq!(game.stats().players.len(), 3);
+    }
+
+    #[test]
+    fn round_limit_needs_a_sole_leader_among_everyone() {
+        let mut game = Match::new(MatchFormat::BestOf(3), rules());
+        game.set_players(4);
+        let [p1, p2, p3, ..] = ButtonRole::ALL;
+        play(&mut game, p1, 200);
+        play(&mut game, p2, 200);
+        assert_eq!(play(&mut game, p3, 200).match_winner, None);
+        assert!(game.is_over());
+    }
+}
@@ -0,0 +1,451 @@
+use heapless::Deque;
+
+use crate::{ButtonEvent, ButtonRole, Micros, MAX_PLAYERS};
+
+/// What a recognized gesture asks the game to do
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum GestureAction {
+    /// Abort the game and go back to waiting
+    Reset,
+    /// The player who made the gesture gives up the match
+    Forfeit,
+}
+
+/// Button combination making up a gesture
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum GesturePattern {
+    /// All these buttons held together for `hold`, a single one makes it its player's gesture
+    ///
+    /// While a chord of more buttons, these among them, is held, this one waits for it and is
+    /// done once it fires.
+    Chord {
+        buttons: &'static [ButtonRole],
+        hold: Micros,
+    },
+    /// These presses in order, each one within `within` of the previous
+    Sequence {
+        presses: &'static [ButtonRole],
+        within: Micros,
+    },
+    /// `tap` pressed while `hold` is held for at least `min_hold`
+    HoldTap {
+        hold: ButtonRole,
+        tap: ButtonRole,
+        min_hold: Micros,
+    },
+}
+
+/// A pattern mapped to its action, declared as data
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Gesture {
+    pub pattern: GesturePattern,
+    pub action: GestureAction,
+}
+
+impl Gesture {
+    /// The first two buttons held for 3 s, the historical reset combo
+    pub const RESET: Gesture = Gesture {
+        pattern: GesturePattern::Chord {
+            buttons: &[ButtonRole::PLAYER_1, ButtonRole::PLAYER_2],
+            hold: 3_000_000,
+        },
+        action: GestureAction::Reset,
+    };
+}
+
+/// A gesture was completed
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Recognized {
+    pub action: GestureAction,
+    /// Player who completed the gesture, `None` for a chord of several buttons
+    pub player: Option<ButtonRole>,
+    pub at: Micros,
+}
+
+// Longest sequence that can be recognized
+const MAX_SEQUENCE: usize = 8;
+
+/// Matches [`ButtonEvent`]s against a table of [`Gesture`]s
+///
+/// Chords complete with time, [`Self::poll`] them at [`Self::deadline`].
+pub struct GestureRecognizer<'a> {
+    gestures: &'a [Gesture],
+    pressed_at: [Option<Micros>; MAX_PLAYERS],
+    recent_presses: Deque<(ButtonRole, Micros), MAX_SEQUENCE>,
+    /// Chords already fired during the current hold, by index in `gestures`
+    fired: u32,
+    /// Presses neither complete nor start a sequence
+    sequences_paused: bool,
+}
+
+impl<'a> GestureRecognizer<'a> {
+    /// Only the first 32 gestures are considered
+    pub fn new(gestures: &'a [Gesture]) -> Self {
+        Self {
+            gestures: &gestures[..gestures.len().min(32)],
+            pressed_at: [None; MAX_PLAYERS],
+            recent_presses: Deque::new(),
+            fired: 0,
+            sequences_paused: false,
+        }
+    }
+
+    /// Ignore presses for sequences while `paused`, e.g. when players mash during a round.
+    /// Presses from before the pause don't carry over into a sequence after it
+    pub fn pause_sequences(&mut self, paused: bool) {
+        if paused {
+            self.recent_presses.clear();
+        }
+        self.sequences_paused = paused;
+    }
+
+    /// Feed the next button event, presses can complete a sequence or a hold-tap
+    pub fn on_event(&mut self, event: &ButtonEvent) -> Option<Recognized> {
+        match *event {
+            ButtonEvent::Pressed { player, at } => {
+                self.pressed_at[player.index()] = Some(at);
+                if !self.sequences_paused {
+                    if self.recent_presses.is_full() {
+                        self.recent_presses.pop_front();
+                    }
+                    let _ = self.recent_presses.push_back((player, at));
+                }
+                self.match_press(player, at)
+            }
+            ButtonEvent::Released { player, .. } => {
+                self.pressed_at[player.index()] = None;
+                self.rearm_chords(player);
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// When the next chord completes if its buttons stay held
+    pub fn deadline(&self) -> Option<Micros> {
+        self.pending_chords().map(|(_, due)| due).min()
+    }
+
+    /// Chord completed at `now`, at most one per call
+    pub fn poll(&mut self, now: Micros) -> Option<Recognized> {
+        let (i, due) = self
+            .pending_chords()
+            .filter(|&(_, due)| due <= now)
+            .min_by_key(|&(_, due)| due)?;
+        let GesturePattern::Chord { buttons, .. } = self.gestures[i].pattern else {
+            return None;
+        };
+        // The chords it's made of are done for this hold too
+        self.fired |= self
+            .chords_within(buttons)
+            .fold(0, |fired, j| fired | 1 << j);
+        let player = match buttons {
+            &[one] => Some(one),
+            _ => None,
+        };
+        Some(Recognized {
+            action: self.gestures[i].action,
+            player,
+            at: due,
+        })
+    }
+
+    // Unfired chords with every button down and no larger chord held, and when they complete
+    fn pending_chords(&self) -> impl Iterator<Item = (usize, Micros)> + '_ {
+        self.gestures
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| self.fired & (1 << i) == 0)
+            .filter_map(|(i, gesture)| match gesture.pattern {
+                GesturePattern::Chord { buttons, hold } => {
+                    let last_press = self.held_since(buttons)?;
+                    let yields = self.gestures.iter().any(|larger| match larger.pattern {
+                        GesturePattern::Chord {
+                            buttons: larger, ..
+                        } => {
+                            larger.len() > buttons.len()
+                                && buttons.iter().all(|b| larger.contains(b))
+                                && self.held_since(larger).is_some()
+                        }
+                        _ => false,
+                    });
+                    (!yields).then_some((i, last_press + hold))
+                }
+                _ => None,
+            })
+    }
+
+    // Last press of `buttons`, `None` unless they're all down
+    fn held_since(&self, buttons: &[ButtonRole]) -> Option<Micros> {
+        buttons
+            .iter()
+            .map(|b| self.pressed_at[b.index()])
+            .try_fold(0, |last, at| Some(last.max(at?)))
+    }
+
+    // Chords made of some of `buttons`, by index in `gestures`
+    fn chords_within<'b>(&'b self, buttons: &'b [ButtonRole]) -> impl Iterator<Item = usize> + 'b {
+        self.gestures
+            .iter()
+            .enumerate()
+            .filter_map(move |(i, gesture)| match gesture.pattern {
+                GesturePattern::Chord {
+                    buttons: within, ..
+                } => within.iter().all(|b| buttons.contains(b)).then_some(i),
+                _ => None,
+            })
+    }
+
+    fn rearm_chords(&mut self, released: ButtonRole) {
+        for (i, gesture) in self.gestures.iter().enumerate() {
+            if let GesturePattern::Chord { buttons, .. } = gesture.pattern {
+                if buttons.contains(&released) {
+                    self.fired &= !(1 << i);
+                }
+            }
+        }
+    }
+
+    fn match_press(&mut self, player: ButtonRole, at: Micros) -> Option<Recognized> {
+        let gesture = self.gestures.iter().find(|g| match g.pattern {
+            GesturePattern::Sequence { presses, within } => {
+                !self.sequences_paused && self.ends_with(presses, within)
+            }
+            GesturePattern::HoldTap {
+                hold,
+                tap,
+                min_hold,
+            } => {
+                tap == player
+                    && hold != tap
+                    && self.pressed_at[hold.index()]
+                        .is_some_and(|held| at.saturating_sub(held) >= min_hold)
+            }
+            GesturePattern::Chord { .. } => false,
+        })?;
+        // A completed sequence can't be reused as the start of the next one
+        self.recent_presses.clear();
+        Some(Recognized {
+            action: gesture.action,
+            player: Some(player),
+            at,
+        })
+    }
+
+    fn ends_with(&self, presses: &[ButtonRole], within: Micros) -> bool {
+        let recent = self.recent_presses.len();
+        if presses.is_empty() || presses.len() > recent {
+            return false;
+        }
+        let tail = self.recent_presses.iter().skip(recent - presses.len());
+        let mut previous: Option<Micros> = None;
+        for (&(player, at), expected) in tail.zip(presses) {
+            if player != *expected || previous.is_some_and(|p| at.saturating_sub(p) > within) {
+                return false;
+            }
+            previous = Some(at);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const P1: ButtonRole = ButtonRole::PLAYER_1;
+    const P2: ButtonRole = ButtonRole::PLAYER_2;
+
+    const GESTURES: [Gesture; 3] = [
+        Gesture::RESET,
+        Gesture {
+            pattern: GesturePattern::Sequence {
+                presses: &[P1, P2, P1],
+                within: 500_000,
+            },
+            action: GestureAction::Reset,
+        },
+        Gesture {
+            pattern: GesturePattern::HoldTap {
+                hold: P2,
+                tap: P1,
+                min_hold: 1_000_000,
+            },
+            action: GestureAction::Forfeit,
+        },
+    ];
+
+    fn press(player: ButtonRole, at: Micros) -> ButtonEvent {
+        ButtonEvent::Pressed { player, at }
+    }
+
+    fn release(player: ButtonRole, at: Micros) -> ButtonEvent {
+        ButtonEvent::Released {
+            player,
+            at,
+            held: 0,
+        }
+    }
+
+    #[test]
+    fn chord_fires_once_per_hold() {
+        let mut gestures = GestureRecognizer::new(&[Gesture::RESET]);
+        assert_eq!(gestures.on_event(&press(P1, 0)), None);
+        assert_eq!(gestures.deadline(), None);
+        gestures.on_event(&press(P2, 200_000));
+        assert_eq!(gestures.deadline(), Some(3_200_000));
+        assert_eq!(gestures.poll(3_199_999), None);
+        assert_eq!(
+            gestures.poll(3_250_000),
+            Some(Recognized {
+                action: GestureAction::Reset,
+                player: None,
+                at: 3_200_000
+            })
+        );
+        assert_eq!(gestures.poll(9_000_000), None);
+        assert_eq!(gestures.deadline(), None);
+
+        // Releasing one button re-arms the chord
+        gestures.on_event(&release(P1, 9_000_000));
+        gestures.on_event(&press(P1, 9_100_000));
+        assert_eq!(gestures.deadline(), Some(12_100_000));
+    }
+
+    #[test]
+    fn chord_broken_before_completion() {
+        let mut gestures = GestureRecognizer::new(&[Gesture::RESET]);
+        gestures.on_event(&press(P1, 0));
+        gestures.on_event(&press(P2, 0));
+        gestures.on_event(&release(P2, 2_999_000));
+        assert_eq!(gestures.poll(3_000_000), None);
+    }
+
+    #[test]
+    fn sequence_within_time() {
+        let mut gestures = GestureRecognizer::new(&GESTURES);
+        gestures.on_event(&press(P1, 0));
+        gestures.on_event(&release(P1, 100_000));
+        gestures.on_event(&press(P2, 400_000));
+        gestures.on_event(&release(P2, 500_000));
+        assert_eq!(
+            gestures.on_event(&press(P1, 800_000)),
+            Some(Recognized {
+                action: GestureAction::Reset,
+                player: Some(P1),
+                at: 800_000
+            })
+        );
+        // The last press doesn't start a new sequence
+        gestures.on_event(&press(P2, 900_000));
+        assert_eq!(gestures.on_event(&press(P1, 1_000_000)), None);
+    }
+
+    #[test]
+    fn sequence_too_slow() {
+        let mut gestures = GestureRecognizer::new(&GESTURES);
+        gestures.on_event(&press(P1, 0));
+        gestures.on_event(&press(P2, 600_000));
+        assert_eq!(gestures.on_event(&press(P1, 700_000)), None);
+    }
+
+    #[test]
+    fn single_button_chord_is_its_players() {
+        let hold = forfeit(&[P2]);
+        let mut gestures = GestureRecognizer::new(core::slice::from_ref(&hold));
+        gestures.on_event(&press(P2, 1_000_000));
+        assert_eq!(
+            gestures.poll(6_000_000),
+            Some(Recognized {
+                action: GestureAction::Forfeit,
+                player: Some(P2),
+                at: 6_000_000
+            })
+        );
+    }
+
+    // A single button chord, held long
+    fn forfeit(button: &'static [ButtonRole; 1]) -> Gesture {
+        Gesture {
+            pattern: GesturePattern::Chord {
+                buttons: button,
+                hold: 5_000_000,
+            },
+            action: GestureAction::Forfeit,
+        }
+    }
+
+    #[test]
+    fn chord_of_both_buttons_overrides_either_alone() {
+        let gestures = [Gesture::RESET, forfeit(&[P1]), forfeit(&[P2])];
+        let mut gestures = GestureRecognizer::new(&gestures);
+        gestures.on_event(&press(P1, 0));
+        assert_eq!(gestures.deadline(), Some(5_000_000));
+        // P1's forfeit would be due before the reset, it waits for it instead
+        gestures.on_event(&press(P2, 2_500_000));
+        assert_eq!(gestures.deadline(), Some(5_500_000));
+        assert_eq!(gestures.poll(5_000_000), None);
+        assert_eq!(
+            gestures.poll(5_500_000),
+            Some(Recognized {
+                action: GestureAction::Reset,
+                player: None,
+                at: 5_500_000
+            })
+        );
+        // Neither forfeit fires for the rest of the hold, nor once a button of it is let go
+        assert_eq!(gestures.poll(20_000_000), None);
+        gestures.on_event(&release(P2, 20_000_000));
+        assert_eq!(gestures.poll(30_000_000), None);
+
+        // Let go before the reset completes, the forfeit still counts from the first press
+        gestures.on_event(&release(P1, 30_000_000));
+        gestures.on_event(&press(P1, 31_000_000));
+        gestures.on_event(&press(P2, 32_000_000));
+        gestures.on_event(&release(P2, 34_000_000));
+        assert_eq!(gestures.deadline(), Some(36_000_000));
+        assert_eq!(
+            gestures.poll(36_000_000).map(|r| (r.action, r.player)),
+            Some((GestureAction::Forfeit, Some(P1)))
+        );
+    }
+
+    #[test]
+    fn paused_sequences_ignore_mashing() {
+        let mut gestures = GestureRecognizer::new(&GESTURES);
+        gestures.on_event(&press(P1, 0));
+        gestures.on_event(&press(P2, 100_000));
+        gestures.pause_sequences(true);
+        assert_eq!(gestures.on_event(&press(P1, 200_000)), None);
+        for at in [300_000, 400_000, 500_000] {
+            assert_eq!(gestures.on_event(&press(P1, at)), None);
+            assert_eq!(gestures.on_event(&press(P2, at + 50_000)), None);
+        }
+        // Presses from the pause don't finish one after it either
+        gestures.pause_sequences(false);
+        assert_eq!(gestures.on_event(&press(P1, 600_000)), None);
+        gestures.on_event(&press(P2, 700_000));
+        assert!(gestures.on_event(&press(P1, 800_000)).is_some());
+        // Chords still work while paused
+        let mut gestures = GestureRecognizer::new(&GESTURES);
+        gestures.pause_sequences(true);
+        gestures.on_event(&press(P1, 0));
+        gestures.on_event(&press(P2, 0));
+        assert!(gestures.poll(3_000_000).is_some());
+    }
+
+    #[test]
+    fn hold_one_tap_the_other() {
+        let mut gestures = GestureRecognizer::new(&GESTURES);
+        gestures.on_event(&press(P2, 0));
+        assert_eq!(gestures.on_event(&press(P1, 500_000)), None);
+        gestures.on_event(&release(P1, 600_000));
+        let forfeit = gestures.on_event(&press(P1, 1_200_000)).unwrap();
+        assert_eq!(forfeit.action, GestureAction::Forfeit);
+        assert_eq!(forfeit.player, Some(P1));
+    }
+}
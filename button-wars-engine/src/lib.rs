@@ -0,0 +1,64 @@
+//! Hardware-independent core of Pico Button Wars.
+//!
+//! The engine never touches GPIO nor the clock: the firmware feeds it button
+//! presses and timestamps as plain data and acts on the decisions it returns
+//! (round winner, match winner, end-of-game stats). This keeps the rules
+//! testable on a Linux host with `cargo test`.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub mod bot;
+pub mod button;
+pub mod calibration;
+pub mod capture;
+pub mod config;
+pub mod console;
+pub mod crc;
+pub mod debounce;
+pub mod event;
+pub mod foreperiod;
+pub mod format;
+pub mod game;
+pub mod gesture;
+pub mod player;
+pub mod practice;
+pub mod records;
+pub mod rng;
+pub mod round;
+pub mod state;
+pub mod stats;
+pub mod storage;
+
+pub use bot::{Bot, ReactionDistribution};
+pub use button::{ButtonDecoder, ButtonEvent, ButtonEvents, ClickConfig};
+pub use calibration::DebounceCalibration;
+pub use capture::{CapturedEdge, EdgeCaptureDecoder};
+pub use config::{Config, LedTimings, PlayerPins, Setting, Value};
+pub use console::{Command, GameStatus, LineBuffer, ParseError};
+pub use crc::crc32;
+pub use debounce::{
+    DebounceKind, DebounceStrategy, DebouncedEdge, Debouncer, Integrator, SleepVerify, TimeLock,
+};
+pub use event::GameEvent;
+pub use foreperiod::Foreperiod;
+pub use format::MatchFormat;
+pub use game::{Match, RoundOutcome, MAX_ROUNDS};
+pub use gesture::{Gesture, GestureAction, GesturePattern, GestureRecognizer, Recognized};
+pub use player::{ButtonRole, MAX_PLAYERS};
+pub use practice::{PracticeSession, PracticeSummary, Trial};
+pub use records::Records;
+pub use rng::{RandomSource, Xoshiro256};
+pub use round::{
+    FalseStartRule, PlayerResult, PlayerResults, Round, RoundEvent, RoundResult, RoundRules,
+    ScoringMetric, TiePolicy, Verdict,
+};
+pub use state::{GameState, StateHooks, StateMachine, TransitionError};
+pub use stats::{MatchStats, PlayerStats, ReactionStats};
+pub use storage::{Key, Store, StoreError};
+
+/// Timestamps and durations handled by the engine, in microseconds since boot
+pub type Micros = u64;
+
+/// Convert a duration in [`Micros`] to whole milliseconds for display
+pub const fn as_millis(us: Micros) -> u64 {
+    us / 1000
+}
@@ -0,0 +1,53 @@
+/// Most players a match can have, sized for the RP2040 pins and PIO state machines
+pub const MAX_PLAYERS: usize = 8;
+
+/// A player, by its position in the firmware's pin table
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Hash)]
+pub struct ButtonRole(u8);
+
+impl ButtonRole {
+    pub const PLAYER_1: ButtonRole = ButtonRole(0);
+    pub const PLAYER_2: ButtonRole = ButtonRole(1);
+
+    /// Every possible player, a match only uses the first few
+    pub const ALL: [ButtonRole; MAX_PLAYERS] = {
+        let mut all = [ButtonRole(0); MAX_PLAYERS];
+        let mut i = 0;
+        while i < MAX_PLAYERS {
+            all[i] = ButtonRole(i as u8);
+            i += 1;
+        }
+        all
+    };
+
+    /// `None` past [`MAX_PLAYERS`]
+    pub const fn new(index: usize) -> Option<Self> {
+        match index < MAX_PLAYERS {
+            true => Some(ButtonRole(index as u8)),
+            false => None,
+        }
+    }
+
+    /// Position of the player in per-player arrays
+    pub const fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    /// The first `count` players
+    pub fn first(count: usize) -> impl Iterator<Item = ButtonRole> {
+        Self::ALL.into_iter().take(count)
+    }
+}
+
+impl core::fmt::Display for ButtonRole {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "Player{}", self.0 + 1)
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for ButtonRole {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "Player{=u8}", self.0 + 1)
+    }
+}
@@ -0,0 +1,214 @@
+use heapless::Vec;
+
+use crate::{ButtonRole, Micros, ReactionStats};
+
+/// Most trials in a practice session, as many as the stats can summarize
+pub const MAX_TRIALS: usize = crate::stats::MAX_SAMPLES;
+
+/// Outcome of one practice trial
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Trial {
+    /// Pressed after GO, reaction time from GO
+    Reacted(Micros),
+    /// Pressed before GO
+    FalseStart,
+    /// Pressed after GO but faster than the minimum reaction, from GO
+    Anticipated(Micros),
+    /// Nothing pressed before the timeout
+    NoPress,
+}
+
+/// A player alone against the clock for a fixed number of trials
+///
+/// Each trial is a hold phase then [`Self::go`], the first press or [`Self::poll`] at
+/// [`Self::deadline`] ends it.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct PracticeSession {
+    player: ButtonRole,
+    trials: usize,
+    timeout: Micros,
+    min_reaction: Micros,
+    go_at: Option<Micros>,
+    results: Vec<Trial, MAX_TRIALS>,
+}
+
+impl PracticeSession {
+    /// `trials` is clamped to 1..=[`MAX_TRIALS`], `timeout` counts from GO and presses
+    /// faster than `min_reaction` are anticipations
+    pub fn new(player: ButtonRole, trials: usize, timeout: Micros, min_reaction: Micros) -> Self {
+        Self {
+            player,
+            trials: trials.clamp(1, MAX_TRIALS),
+            timeout,
+            min_reaction,
+            go_at: None,
+            results: Vec::new(),
+        }
+    }
+
+    pub fn player(&self) -> ButtonRole {
+        self.player
+    }
+
+    pub fn trials(&self) -> usize {
+        self.trials
+    }
+
+    /// Index of the trial being played, from 0
+    pub fn current_trial(&self) -> usize {
+        self.results.len()
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.results.len() >= self.trials
+    }
+
+    pub fn results(&self) -> &[Trial] {
+        &self.results
+    }
+
+    /// LEDs went off for the current trial
+    pub fn go(&mut self, at: Micros) {
+        if !self.is_over() {
+            self.go_at = Some(at);
+        }
+    }
+
+    /// Press of the player, ends the current trial either way
+    pub fn on_press(&mut self, at: Micros) -> Option<Trial> {
+        let trial = match self.go_at {
+            Some(go_at) if at >= go_at + self.min_reaction => Trial::Reacted(at - go_at),
+            Some(go_at) if at >= go_at => Trial::Anticipated(at - go_at),
+            _ => Trial::FalseStart,
+        };
+        self.finish(trial)
+    }
+
+    /// When the current trial times out, `None` before GO
+    pub fn deadline(&self) -> Option<Micros> {
+        self.go_at.map(|go_at| go_at + self.timeout)
+    }
+
+    pub fn poll(&mut self, now: Micros) -> Option<Trial> {
+        self.deadline().filter(|&due| now >= due)?;
+        self.finish(Trial::NoPress)
+    }
+
+    fn finish(&mut self, trial: Trial) -> Option<Trial> {
+        if self.is_over() {
+            return None;
+        }
+        self.go_at = None;
+        self.results.push(trial).ok()?;
+        Some(trial)
+    }
+
+    /// Fastest reaction of the session so far
+    pub fn best(&self) -> Option<Micros> {
+        self.reaction_times().min()
+    }
+
+    pub fn summary(&self) -> PracticeSummary {
+        let count = |kind: Trial| self.results.iter().filter(|&&t| t == kind).count();
+        PracticeSummary {
+            player: self.player,
+            trials: self.results.len(),
+            false_starts: count(Trial::FalseStart),
+            anticipations: self
+                .results
+                .iter()
+                .filter(|t| matches!(t, Trial::Anticipated(_)))
+                .count(),
+            no_presses: count(Trial::NoPress),
+            reaction: ReactionStats::from_samples(self.reaction_times()),
+        }
+    }
+
+    fn reaction_times(&self) -> impl Iterator<Item = Micros> + '_ {
+        self.results.iter().filter_map(|trial| match trial {
+            Trial::Reacted(time) => Some(*time),
+            _ => None,
+        })
+    }
+}
+
+/// End of session summary, mean, best and consistency are in `reaction`
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PracticeSummary {
+    pub player: ButtonRole,
+    pub trials: usize,
+    pub false_starts: usize,
+    pub anticipations: usize,
+    pub no_presses: usize,
+    /// Over the trials pressed after GO, anticipations left out
+    pub reaction: Option<ReactionStats>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const P1: ButtonRole = ButtonRole::PLAYER_1;
+
+    fn play(session: &mut PracticeSession, go_at: Micros, press_at: Option<Micros>) -> Trial {
+        session.go(go_at);
+        match press_at {
+            Some(at) => session.on_press(at),
+            None => session.poll(session.deadline().unwrap()),
+        }
+        .unwrap()
+    }
+
+    #[test]
+    fn trials_end_on_press_false_start_or_timeout() {
+        let mut session = PracticeSession::new(P1, 3, 1_000_000, 100_000);
+        assert_eq!(session.deadline(), None);
+        assert_eq!(session.on_press(500), Some(Trial::FalseStart));
+        assert_eq!(
+            play(&mut session, 10_000, Some(260_000)),
+            Trial::Reacted(250_000)
+        );
+        session.go(2_000_000);
+        assert_eq!(session.deadline(), Some(3_000_000));
+        assert_eq!(session.poll(2_999_999), None);
+        assert_eq!(session.poll(3_000_000), Some(Trial::NoPress));
+        assert!(session.is_over());
+        // Nothing counts once the session is over
+        assert_eq!(session.on_press(4_000_000), None);
+        assert_eq!(session.current_trial(), 3);
+    }
+
+    #[test]
+    fn summary_over_the_reacted_trials() {
+        let mut session = PracticeSession::new(P1, 6, 1_000_000, 100_000);
+        for (go_at, press_at) in [
+            (0, Some(200_000)),
+            (1_000_000, Some(1_300_000)),
+            (2_000_000, None),
+            (3_000_000, Some(3_250_000)),
+            // Too fast to be a reaction, doesn't make the best
+            (4_000_000, Some(4_030_000)),
+        ] {
+            play(&mut session, go_at, press_at);
+        }
+        session.on_press(5_000_000);
+        assert_eq!(session.best(), Some(200_000));
+        assert_eq!(session.results()[4], Trial::Anticipated(30_000));
+
+        let summary = session.summary();
+        assert_eq!(
+            (
+                summary.trials,
+                summary.false_starts,
+                summary.anticipations,
+                summary.no_presses
+            ),
+            (6, 1, 1, 1)
+        );
+        let reaction = summary.reaction.unwrap();
+        assert_eq!((reaction.count, reaction.mean), (3, 250_000));
+        assert_eq!(reaction.min, 200_000);
+    }
+}
@@ -0,0 +1,306 @@
+use crate::{ButtonRole, MatchFormat, MatchStats, Micros, PracticeSummary, MAX_PLAYERS};
+
+/// Match modes with records of their own, every match format preset
+pub const MODES: usize = MatchFormat::PRESETS.len();
+
+/// All-time records of the board, as kept in the [`Store`](crate::Store)
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Records {
+    /// Fastest reaction ever, match or practice, and whose it was
+    pub best_reaction: Option<(ButtonRole, Micros)>,
+    /// Fastest reaction of each match mode, by [`MatchFormat::preset_index`]
+    pub mode_best: [Option<Micros>; MODES],
+    /// Personal best of each player in practice, indexed by [`ButtonRole::index`]
+    pub practice_bests: [Option<Micros>; MAX_PLAYERS],
+    /// Matches won, indexed by [`ButtonRole::index`]
+    pub wins: [u32; MAX_PLAYERS],
+    pub matches_played: u32,
+}
+
+// Magic, best reaction holder and time, every mode best, every practice best, every win
+// count, matches played
+const SIZE: usize = 4 + 1 + 4 + 4 * MODES + 4 * MAX_PLAYERS + 4 * MAX_PLAYERS + 4;
+// Player byte of no best reaction
+const NOBODY: u8 = 0xFF;
+
+impl Records {
+    pub const SIZE: usize = SIZE;
+    // The store checks the CRC, the magic tells this layout apart from later ones
+    const MAGIC: [u8; 4] = *b"BWR1";
+
+    /// Count a finished match, true when it set a reaction record
+    ///
+    /// Only the first `humans` seats hold records, the bot ones after them don't.
+    /// Custom formats only count toward the all-time best.
+    pub fn record_match(
+        &mut self,
+        format: MatchFormat,
+        stats: &MatchStats,
+        winner: Option<ButtonRole>,
+        humans: usize,
+    ) -> bool {
+        self.matches_played = self.matches_played.saturating_add(1);
+        if let Some(winner) = winner.filter(|winner| winner.index() < humans) {
+            let wins = &mut self.wins[winner.index()];
+            *wins = wins.saturating_add(1);
+        }
+        let mut new_record = false;
+        for player in stats.players.iter().filter(|p| p.player.index() < humans) {
+            if let Some(reaction) = player.reaction {
+                new_record |= self.record_reaction(player.player, reaction.min);
+                if let Some(best) = format.preset_index().map(|mode| &mut self.mode_best[mode]) {
+                    new_record |= improve(best, reaction.min);
+                }
+            }
+        }
+        new_record
+    }
+
+    /// Count a practice session, true when it beat the player's personal best
+    pub fn record_practice(&mut self, summary: &PracticeSummary) -> bool {
+        summary.reaction.is_some_and(|reaction| {
+            self.record_reaction(summary.player, reaction.min);
+            improve(
+                &mut self.practice_bests[summary.player.index()],
+                reaction.min,
+            )
+        })
+    }
+
+    pub fn personal_best(&self, player: ButtonRole) -> Option<Micros> {
+        self.practice_bests[player.index()]
+    }
+
+    /// Fastest practice reaction of any player
+    pub fn practice_best(&self) -> Option<Micros> {
+        self.practice_bests.iter().flatten().min().copied()
+    }
+
+    fn record_reaction(&mut self, player: ButtonRole, time: Micros) -> bool {
+        let beaten = self.best_reaction.is_none_or(|(_, best)| time < best);
+        if beaten {
+            self.best_reaction = Some((player, time));
+        }
+        beaten
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut bytes = [0; Self::SIZE];
+        bytes[..4].copy_from_slice(&Self::MAGIC);
+        let (holder, best) = self.best_reaction.map_or((NOBODY, None), |(player, time)| {
+            (player.index() as u8, Some(time))
+        });
+        bytes[4] = holder;
+        let words = core::iter::once(micros_word(best))
+            .chain(self.mode_best.iter().map(|&best| micros_word(best)))
+            .chain(self.practice_bests.iter().map(|&best| micros_word(best)))
+            .chain(self.wins)
+            .chain([self.matches_played]);
+        for (chunk, word) in bytes[5..].chunks_exact_mut(4).zip(words) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// `None` for another layout
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let bytes = bytes.get(..Self::SIZE)?;
+        if bytes[..4] != Self::MAGIC {
+            return None;
+        }
+        let mut words = words(&bytes[5..]);
+        let mut records = Self {
+            best_reaction: best_reaction(bytes[4], words.next()?),
+            ..Self::default()
+        };
+        for best in records.mode_best.iter_mut() {
+            *best = word_micros(words.next()?);
+        }
+        for best in records.practice_bests.iter_mut() {
+            *best = word_micros(words.next()?);
+        }
+        for wins in records.wins.iter_mut() {
+            *wins = words.next()?;
+        }
+        records.matches_played = words.next()?;
+        Some(records)
+    }
+}
+
+// Keep `time` if it beats `best`, true when it did
+fn improve(best: &mut Option<Micros>, time: Micros) -> bool {
+    let beaten = best.is_none_or(|best| time < best);
+    if beaten {
+        *best = Some(time);
+    }
+    beaten
+}
+
+fn words(bytes: &[u8]) -> impl Iterator<Item = u32> + '_ {
+    bytes
+        .chunks_exact(4)
+        .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+}
+
+fn best_reaction(holder: u8, word: u32) -> Option<(ButtonRole, Micros)> {
+    Some((*ButtonRole::ALL.get(holder as usize)?, word_micros(word)?))
+}
+
+// 0 for none, a reaction is never that fast
+fn micros_word(time: Option<Micros>) -> u32 {
+    time.map_or(0, |us| us.min(u32::MAX as Micros) as u32)
+}
+
+fn word_micros(word: u32) -> Option<Micros> {
+    (word != 0).then_some(word as Micros)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PlayerResult, PlayerResults, PracticeSession, RoundResult, Verdict};
+
+    const P1: ButtonRole = ButtonRole::PLAYER_1;
+    const P2: ButtonRole = ButtonRole::PLAYER_2;
+
+    // Stats of a single round both players reacted in
+    fn stats(p1: Micros, p2: Micros) -> MatchStats {
+        let reacted = |reaction_time| PlayerResult::Reacted {
+            reaction_time,
+            release_time: None,
+            penalized: false,
+        };
+        let round = RoundResult {
+            verdict: Verdict::Fastest,
+            winner: Some(if p1 < p2 { P1 } else { P2 }),
+            response_time: Some(p1.min(p2)),
+            reaction_time: Some(p1.min(p2)),
+            release_time: None,
+            margin: Some(p1.abs_diff(p2)),
+            players: PlayerResults::from([reacted(p1), reacted(p2)]),
+        };
+        MatchStats::from_rounds(2, &[round])
+    }
+
+    #[test]
+    fn matches_count_wins_and_mode_bests() {
+        let mut records = Records::default();
+        assert!(records.record_match(
+            MatchFormat::SuddenDeath,
+            &stats(210_000, 260_000),
+            Some(P1),
+            2
+        ));
+        // Slower in another mode is still that mode's record, not the all-time one
+        assert!(records.record_match(
+            MatchFormat::BestOf(3),
+            &stats(300_000, 240_000),
+            Some(P2),
+            2
+        ));
+        assert!(!records.record_match(
+            MatchFormat::SuddenDeath,
+            &stats(290_000, 250_000),
+            Some(P2),
+            2
+        ));
+
+        assert_eq!(records.best_reaction, Some((P1, 210_000)));
+        assert_eq!(records.mode_best[5], Some(210_000));
+        assert_eq!(records.mode_best[1], Some(240_000));
+        assert_eq!(records.mode_best[0], None);
+        assert_eq!(records.wins[..2], [1, 2]);
+        assert_eq!(records.matches_played, 3);
+
+        // A custom format only competes for the all-time best
+        assert!(records.record_match(MatchFormat::FirstTo(9), &stats(190_000, 400_000), None, 2));
+        assert_eq!(records.best_reaction, Some((P1, 190_000)));
+        assert_eq!(records.matches_played, 4);
+    }
+
+    #[test]
+    fn bot_seats_hold_no_records() {
+        let mut records = Records::default();
+        // P2 is the bot of a single player game, faster and winning
+        assert!(records.record_match(
+            MatchFormat::SuddenDeath,
+            &stats(260_000, 150_000),
+            Some(P2),
+            1
+        ));
+        assert_eq!(records.best_reaction, Some((P1, 260_000)));
+        assert_eq!(records.mode_best[5], Some(260_000));
+        assert_eq!(records.wins, [0; MAX_PLAYERS]);
+        assert_eq!(records.matches_played, 1);
+
+        // The human's own reactions and wins still count
+        assert!(records.record_match(
+            MatchFormat::SuddenDeath,
+            &stats(240_000, 300_000),
+            Some(P1),
+            1
+        ));
+        assert_eq!(records.best_reaction, Some((P1, 240_000)));
+        assert_eq!(records.mode_best[5], Some(240_000));
+        assert_eq!(records.wins[..2], [1, 0]);
+    }
+
+    // Summary of a practice session of a single trial, pressed at `press_at` from GO
+    fn practice(player: ButtonRole, press_at: Micros) -> PracticeSummary {
+        let mut session = PracticeSession::new(player, 1, 1_000_000, 100_000);
+        session.go(0);
+        session.on_press(press_at);
+        session.summary()
+    }
+
+    #[test]
+    fn practice_has_its_own_record() {
+        let mut records = Records::default();
+        records.record_match(
+            MatchFormat::default(),
+            &stats(180_000, 200_000),
+            Some(P1),
+            2,
+        );
+        assert!(records.record_practice(&practice(P2, 220_000)));
+        assert_eq!(records.practice_best(), Some(220_000));
+        assert_eq!(records.best_reaction, Some((P1, 180_000)));
+    }
+
+    #[test]
+    fn personal_best_only_improves() {
+        let mut records = Records::default();
+        assert!(records.record_practice(&practice(P1, 240_000)));
+        assert!(!records.record_practice(&practice(P1, 250_000)));
+        // Slower than the other player's, still a best of their own
+        assert!(records.record_practice(&practice(P2, 300_000)));
+        assert_eq!(records.personal_best(P1), Some(240_000));
+        assert_eq!(records.personal_best(P2), Some(300_000));
+        assert_eq!(records.practice_best(), Some(240_000));
+
+        // A session without any reaction leaves the record alone
+        let mut session = PracticeSession::new(P1, 1, 1_000_000, 100_000);
+        session.on_press(0);
+        assert!(!records.record_practice(&session.summary()));
+        assert_eq!(records.personal_best(P1), Some(240_000));
+        assert_eq!(records.personal_best(ButtonRole::new(2).unwrap()), None);
+    }
+
+    #[test]
+    fn records_round_trip_through_bytes() {
+        let mut records = Records::default();
+        assert_eq!(Records::from_bytes(&records.to_bytes()), Some(records));
+        records.record_match(
+            MatchFormat::default(),
+            &stats(205_000, 230_000),
+            Some(P1),
+            2,
+        );
+        records.record_practice(&practice(P2, 187_500));
+        records.wins[7] = 12;
+        assert_eq!(Records::from_bytes(&records.to_bytes()), Some(records));
+        assert_eq!(Records::from_bytes(&[0xFF; Records::SIZE]), None);
+        assert_eq!(Records::from_bytes(&records.to_bytes()[..10]), None);
+    }
+}
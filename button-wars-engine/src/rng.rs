@@ -0,0 +1,142 @@
+/// Source of randomness for the engine, implemented by the firmware RNG
+pub trait RandomSource {
+    fn next_u64(&mut self) -> u64;
+
+    /// Uniform in `0..bound`, without the modulo bias, 0 for a `bound` of 0
+    fn below(&mut self, bound: u64) -> u64 {
+        if bound == 0 {
+            return 0;
+        }
+        // Lemire's multiply and shift, redrawing the few products that would favor low values
+        let threshold = bound.wrapping_neg() % bound;
+        loop {
+            let product = self.next_u64() as u128 * bound as u128;
+            if product as u64 >= threshold {
+                return (product >> 64) as u64;
+            }
+        }
+    }
+
+    /// Uniform in `from..=to`, `from` when the range is empty
+    fn range_inclusive(&mut self, from: u64, to: u64) -> u64 {
+        match (to.checked_sub(from), from >= to) {
+            (_, true) | (None, _) => from,
+            (Some(u64::MAX), _) => self.next_u64(),
+            (Some(span), _) => from + self.below(span + 1),
+        }
+    }
+}
+
+/// xoshiro256**, fast and well distributed over all 64 bits, not for cryptography
+///
+/// The same seed always gives the same draws, log it to replay a game.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Xoshiro256 {
+    state: [u64; 4],
+}
+
+impl Xoshiro256 {
+    /// State expanded from `seed` with SplitMix64, as recommended by the authors
+    pub const fn from_seed(seed: u64) -> Self {
+        let mut splitmix = seed;
+        let mut state = [0; 4];
+        let mut i = 0;
+        while i < 4 {
+            splitmix = splitmix.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            let mut z = splitmix;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            state[i] = z ^ (z >> 31);
+            i += 1;
+        }
+        Self { state }
+    }
+}
+
+impl RandomSource for Xoshiro256 {
+    fn next_u64(&mut self) -> u64 {
+        let s = &mut self.state;
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_the_reference_generator() {
+        let mut rng = Xoshiro256 {
+            state: [1, 2, 3, 4],
+        };
+        assert_eq!(rng.next_u64(), 11_520);
+        assert_eq!(rng.next_u64(), 0);
+        assert_eq!(rng.next_u64(), 1_509_978_240);
+        // SplitMix64 of 0, first output of the reference implementation
+        assert_eq!(Xoshiro256::from_seed(0).state[0], 0xE220_A839_7B1D_CDAF);
+    }
+
+    #[test]
+    fn same_seed_same_draws() {
+        let mut a = Xoshiro256::from_seed(42);
+        let mut b = Xoshiro256::from_seed(42);
+        let mut c = Xoshiro256::from_seed(43);
+        let draws: [u64; 8] = core::array::from_fn(|_| a.next_u64());
+        assert_eq!(draws, core::array::from_fn(|_| b.next_u64()));
+        assert_ne!(draws, core::array::from_fn(|_| c.next_u64()));
+    }
+
+    #[test]
+    fn ranges_stay_in_bounds() {
+        let mut rng = Xoshiro256::from_seed(7);
+        for _ in 0..10_000 {
+            assert!((2_000..=5_000).contains(&rng.range_inclusive(2_000, 5_000)));
+            assert!(rng.below(3) < 3);
+        }
+        assert_eq!(rng.range_inclusive(9, 9), 9);
+        assert_eq!(rng.range_inclusive(9, 1), 9);
+        assert_eq!(rng.below(0), 0);
+        rng.range_inclusive(0, u64::MAX);
+    }
+
+    // Chi-squared over the buckets of `draw`, each expected `expected` times
+    fn chi_squared<const N: usize>(draws: usize, mut draw: impl FnMut() -> usize) -> u64 {
+        let mut counts = [0u64; N];
+        for _ in 0..draws {
+            counts[draw()] += 1;
+        }
+        let expected = (draws / N) as u64;
+        counts
+            .iter()
+            .map(|&count| count.abs_diff(expected).pow(2))
+            .sum::<u64>()
+            / expected
+    }
+
+    #[test]
+    fn draws_are_uniform() {
+        let mut rng = Xoshiro256::from_seed(2024);
+        // 9 degrees of freedom, 27.9 is the 0.1% critical value
+        assert!(chi_squared::<10>(100_000, || rng.below(10) as usize) < 28);
+        // The low bits are as good as the high ones, unlike the old LCG
+        assert!(chi_squared::<16>(160_000, || (rng.next_u64() & 0xF) as usize) < 38);
+        assert!(chi_squared::<7>(70_000, || (rng.range_inclusive(3, 9) - 3) as usize) < 23);
+    }
+
+    #[test]
+    fn no_modulo_bias_on_a_huge_bound() {
+        // A third of u64: a plain modulo would land twice as often in the lower half
+        let bound = u64::MAX / 3 * 2;
+        let mut rng = Xoshiro256::from_seed(1);
+        let lower = (0..30_000).filter(|_| rng.below(bound) < bound / 2).count();
+        assert!((14_000..16_000).contains(&lower), "{lower}");
+    }
+}
@@ -0,0 +1,978 @@
+use core::ops::Deref;
+
+use crate::{ButtonRole, Foreperiod, Micros, RandomSource, MAX_PLAYERS};
+
+/// Penalty for a press during the LED hold phase, before GO
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FalseStartRule {
+    /// The offender is out of the round, the others still race after GO
+    LoseRound,
+    /// The round stops at once and the opponent gets the point, only in a duel:
+    /// with more players the offender is out of the round
+    PointToOpponent,
+    /// The offender keeps racing with this much added to its response time
+    TimePenalty(Micros),
+}
+
+/// What happens when the fastest presses are closer than the tie threshold
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TiePolicy {
+    /// Nobody scores but the round counts as played
+    Draw,
+    /// The round is played again
+    Replay,
+    /// One of the tied players is drawn at random
+    CoinFlip,
+}
+
+/// Time a player is ranked on within a round
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ScoringMetric {
+    /// Reaction time, from GO to the press edge
+    PressEdge,
+    /// From GO to the release of the button
+    Release,
+    /// How long the button was held down
+    HoldDuration,
+}
+
+/// Rules a round is played with, picked by the match
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RoundRules {
+    pub scoring: ScoringMetric,
+    pub false_start: FalseStartRule,
+    /// Presses closer than this are considered simultaneous
+    pub tie_threshold: Micros,
+    pub tie_policy: TiePolicy,
+    /// Time after GO given to every player to press and release
+    pub timeout: Micros,
+    /// LED hold before GO
+    pub foreperiod: Foreperiod,
+    /// Presses closer to GO than this are guesses, handled like false starts
+    pub min_reaction: Micros,
+}
+
+impl Default for RoundRules {
+    fn default() -> Self {
+        Self {
+            scoring: ScoringMetric::PressEdge,
+            false_start: FalseStartRule::LoseRound,
+            tie_threshold: 1_000,
+            tie_policy: TiePolicy::Replay,
+            timeout: 3_000_000,
+            foreperiod: Foreperiod::NON_AGING,
+            min_reaction: 100_000,
+        }
+    }
+}
+
+/// How the winner of a round was decided
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Verdict {
+    /// Fastest press after GO
+    Fastest,
+    /// The opponent false started and handed over the point
+    FalseStart,
+    /// Every player false started
+    Void,
+    /// Nobody pressed before the round timeout
+    NoReaction,
+    /// Fastest presses within the tie threshold, settled by the policy
+    Tie(TiePolicy),
+}
+
+/// What a single player did during a round
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PlayerResult {
+    /// Pressed after GO, times are from GO
+    Reacted {
+        reaction_time: Micros,
+        /// `None` if still held when the round timed out
+        release_time: Option<Micros>,
+        /// Raced with a time penalty after a false start
+        penalized: bool,
+    },
+    /// Did not press before the round timeout
+    NoPress,
+    /// Pressed before GO and was not allowed to race
+    FalseStart,
+    /// Pressed after GO but faster than the minimum reaction, and was not allowed to race
+    Anticipated { reaction_time: Micros },
+    /// The round was over before GO, or the player sat it out
+    DidNotRace,
+}
+
+impl PlayerResult {
+    pub fn reaction_time(&self) -> Option<Micros> {
+        match self {
+            PlayerResult::Reacted { reaction_time, .. } => Some(*reaction_time),
+            _ => None,
+        }
+    }
+
+    /// Time ranked by the rules' [`ScoringMetric`] with penalties, as in [`Round::score`]
+    pub fn score(&self, rules: &RoundRules) -> Option<Micros> {
+        let PlayerResult::Reacted {
+            reaction_time,
+            release_time,
+            penalized,
+        } = *self
+        else {
+            return None;
+        };
+        let time = match rules.scoring {
+            ScoringMetric::PressEdge => reaction_time,
+            ScoringMetric::Release => release_time?,
+            ScoringMetric::HoldDuration => release_time?.saturating_sub(reaction_time),
+        };
+        let penalty = match rules.false_start {
+            FalseStartRule::TimePenalty(penalty) if penalized => penalty,
+            _ => 0,
+        };
+        Some(time + penalty)
+    }
+}
+
+/// What every player of a round did, indexed by [`ButtonRole::index`]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct PlayerResults {
+    results: [PlayerResult; MAX_PLAYERS],
+    count: u8,
+}
+
+impl PlayerResults {
+    /// Result of each of the first `count` players
+    pub fn from_fn(count: usize, mut result: impl FnMut(ButtonRole) -> PlayerResult) -> Self {
+        let mut results = [PlayerResult::DidNotRace; MAX_PLAYERS];
+        let count = count.min(MAX_PLAYERS);
+        for player in ButtonRole::first(count) {
+            results[player.index()] = result(player);
+        }
+        Self {
+            results,
+            count: count as u8,
+        }
+    }
+}
+
+impl Deref for PlayerResults {
+    type Target = [PlayerResult];
+
+    fn deref(&self) -> &[PlayerResult] {
+        &self.results[..self.count as usize]
+    }
+}
+
+impl<const N: usize> From<[PlayerResult; N]> for PlayerResults {
+    fn from(results: [PlayerResult; N]) -> Self {
+        Self::from_fn(N, |p| results[p.index()])
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for PlayerResults {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "{}", **self)
+    }
+}
+
+/// Outcome of a single round
+///
+/// Times belong to the winner, or to the fastest player when nobody scored.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RoundResult {
+    pub verdict: Verdict,
+    /// `None` when nobody earned the point, e.g. a void round or a draw
+    pub winner: Option<ButtonRole>,
+    /// Time ranked by the [`ScoringMetric`] with penalties, `None` if nobody raced
+    pub response_time: Option<Micros>,
+    /// From GO to the press edge
+    pub reaction_time: Option<Micros>,
+    /// From GO to the release
+    pub release_time: Option<Micros>,
+    /// How far behind on the ranked time the runner-up was
+    pub margin: Option<Micros>,
+    pub players: PlayerResults,
+}
+
+impl RoundResult {
+    pub fn player(&self, player: ButtonRole) -> PlayerResult {
+        self.players[player.index()]
+    }
+}
+
+/// Notable things happening while a round is fed with presses
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[allow(clippy::large_enum_variant)]
+pub enum RoundEvent {
+    /// `player` pressed at `at`, before GO was given
+    FalseStart {
+        player: ButtonRole,
+        at: Micros,
+    },
+    /// `player` pressed too soon after GO to have reacted to it
+    Anticipated {
+        player: ButtonRole,
+        reaction_time: Micros,
+    },
+    Decided(RoundResult),
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Decision {
+    verdict: Verdict,
+    winner: Option<ButtonRole>,
+    // Player whose times end up in the result
+    timed: Option<ButtonRole>,
+}
+
+#[derive(Clone, Copy, Default, Debug)]
+struct Contender {
+    false_start: Option<Micros>,
+    // Reaction time of the press that was too fast, also counted as the false start
+    anticipation: Option<Micros>,
+    press: Option<Micros>,
+    release: Option<Micros>,
+}
+
+/// A round in progress: LED hold phase until [`Round::go`], then the race
+#[derive(Debug)]
+pub struct Round {
+    index: usize,
+    players: usize,
+    rules: RoundRules,
+    go_at: Option<Micros>,
+    contenders: [Contender; MAX_PLAYERS],
+    // Indexed by [`ButtonRole::index`], players left out of the race
+    sitting_out: [bool; MAX_PLAYERS],
+    // Drawn up front so deciding a round never needs the RNG
+    coin: u64,
+    decision: Option<Decision>,
+    timed_out: bool,
+}
+
+impl Round {
+    /// Round between the first `players` players, from 2 up to [`MAX_PLAYERS`]
+    pub fn new(
+        index: usize,
+        players: usize,
+        rules: RoundRules,
+        rng: &mut impl RandomSource,
+    ) -> Self {
+        Self {
+            index,
+            players: players.clamp(2, MAX_PLAYERS),
+            rules,
+            go_at: None,
+            contenders: [Contender::default(); MAX_PLAYERS],
+            sitting_out: [false; MAX_PLAYERS],
+            coin: rng.next_u64(),
+            decision: None,
+            timed_out: false,
+        }
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn players(&self) -> usize {
+        self.players
+    }
+
+    pub fn rules(&self) -> RoundRules {
+        self.rules
+    }
+
+    pub fn go_at(&self) -> Option<Micros> {
+        self.go_at
+    }
+
+    /// Leave `player` out of the race, their presses are ignored and they can't win
+    pub fn sit_out(&mut self, player: ButtonRole) {
+        if let Some(sitting_out) = self.sitting_out.get_mut(player.index()) {
+            *sitting_out = true;
+        }
+    }
+
+    /// End of the hold phase, presses from now on are part of the race
+    pub fn go(&mut self, at: Micros) {
+        if self.go_at.is_none() {
+            self.go_at = Some(at);
+        }
+    }
+
+    /// Feed a press timestamp, returns what it meant for the round
+    ///
+    /// Presses keep being recorded once the round is decided, until it is complete.
+    pub fn on_press(&mut self, player: ButtonRole, at: Micros) -> Option<RoundEvent> {
+        if !self.is_racing(player) {
+            return None;
+        }
+        let Some(go_at) = self.go_at else {
+            return self.on_false_start(player, at);
+        };
+        // Stamped before GO but only delivered after it, e.g. still debouncing at GO
+        if at < go_at {
+            return self.on_false_start(player, at);
+        }
+        let contender = self.contenders[player.index()];
+        if self.is_out(player) || contender.press.is_some() || self.timed_out {
+            return None;
+        }
+        if at < go_at + self.rules.min_reaction {
+            return self.on_anticipation(player, at - go_at);
+        }
+        self.contenders[player.index()].press = Some(at);
+        self.decide_at(at)
+    }
+
+    /// Feed the release following a press after GO
+    pub fn on_release(&mut self, player: ButtonRole, at: Micros) -> Option<RoundEvent> {
+        if !self.is_racing(player) {
+            return None;
+        }
+        let contender = self.contenders[player.index()];
+        if contender.press.is_none() || contender.release.is_some() || self.timed_out {
+            return None;
+        }
+        self.contenders[player.index()].release = Some(at);
+        self.decide_at(at)
+    }
+
+    fn on_false_start(&mut self, player: ButtonRole, at: Micros) -> Option<RoundEvent> {
+        self.penalize(player, at)
+            .then_some(RoundEvent::FalseStart { player, at })
+    }
+
+    fn on_anticipation(&mut self, player: ButtonRole, reaction_time: Micros) -> Option<RoundEvent> {
+        let at = self.go_at? + reaction_time;
+        if !self.penalize(player, at) {
+            return None;
+        }
+        self.contenders[player.index()].anticipation = Some(reaction_time);
+        Some(RoundEvent::Anticipated {
+            player,
+            reaction_time,
+        })
+    }
+
+    // Apply the false start rule, false when the player was already penalized
+    fn penalize(&mut self, player: ButtonRole, at: Micros) -> bool {
+        let contender = &mut self.contenders[player.index()];
+        if self.decision.is_some() || contender.false_start.is_some() {
+            // Mashing while already penalized changes nothing
+            return false;
+        }
+        contender.false_start = Some(at);
+
+        if self.rules.false_start == FalseStartRule::PointToOpponent && self.players == 2 {
+            let opponent = ButtonRole::new(1 - player.index());
+            self.decide(Verdict::FalseStart, opponent, None);
+        } else if self.contestants().all(|p| self.is_out(p)) {
+            self.decide(Verdict::Void, None, None);
+        }
+        true
+    }
+
+    fn decide_at(&mut self, now: Micros) -> Option<RoundEvent> {
+        let undecided = self.decision.is_none();
+        let result = self.poll(now)?;
+        undecided.then_some(RoundEvent::Decided(result))
+    }
+
+    fn decide(&mut self, verdict: Verdict, winner: Option<ButtonRole>, timed: Option<ButtonRole>) {
+        self.decision = Some(Decision {
+            verdict,
+            winner,
+            timed,
+        });
+    }
+
+    /// Let time pass up to `now`, deciding the round once nobody can beat or tie the fastest
+    /// player anymore, or once the round timed out
+    pub fn poll(&mut self, now: Micros) -> Option<RoundResult> {
+        let go_at = self.go_at?;
+        if now >= go_at + self.rules.timeout {
+            self.timed_out = true;
+        }
+        if self.decision.is_some() {
+            return self.result();
+        }
+        if now < self.decision_deadline()? {
+            return None;
+        }
+        let Some((fastest, response)) = self.fastest() else {
+            self.decide(Verdict::NoReaction, None, None);
+            return self.result();
+        };
+
+        // Everybody within the threshold of the fastest score is part of the tie
+        let limit = response + self.rules.tie_threshold;
+        let mut tied = [fastest; MAX_PLAYERS];
+        let mut tied_count = 0;
+        for (player, _) in self.scores().filter(|&(_, s)| s <= limit) {
+            tied[tied_count] = player;
+            tied_count += 1;
+        }
+
+        if tied_count < 2 {
+            self.decide(Verdict::Fastest, Some(fastest), Some(fastest));
+        } else {
+            let policy = self.rules.tie_policy;
+            let winner = match policy {
+                TiePolicy::Draw | TiePolicy::Replay => None,
+                TiePolicy::CoinFlip => Some(tied[(self.coin % tied_count as u64) as usize]),
+            };
+            self.decide(
+                Verdict::Tie(policy),
+                winner,
+                Some(winner.unwrap_or(fastest)),
+            );
+        }
+        self.result()
+    }
+
+    /// Next instant at which [`Round::poll`] may decide or complete the round
+    ///
+    /// `None` before GO and once the round is complete.
+    pub fn deadline(&self) -> Option<Micros> {
+        if self.is_complete() {
+            return None;
+        }
+        match self.decision {
+            Some(_) => Some(self.go_at? + self.rules.timeout),
+            None => self.decision_deadline(),
+        }
+    }
+
+    // Instant from which the fastest score so far can no longer be beaten nor tied, capped
+    // by the round timeout
+    fn decision_deadline(&self) -> Option<Micros> {
+        let go_at = self.go_at?;
+        let timeout_at = go_at + self.rules.timeout;
+        let Some((_, response)) = self.fastest() else {
+            return Some(timeout_at);
+        };
+        let limit = response + self.rules.tie_threshold;
+
+        let mut deadline = go_at;
+        for player in self.contestants() {
+            if self.is_out(player) || self.score(player).is_some() {
+                continue;
+            }
+            // Latest instant at which this player could still score within the limit
+            let contender = self.contenders[player.index()];
+            let budget = limit.saturating_sub(self.penalty(player));
+            let settles_at = match (self.rules.scoring, contender.press) {
+                (ScoringMetric::PressEdge | ScoringMetric::Release, _) => go_at + budget,
+                (ScoringMetric::HoldDuration, Some(press)) => press + budget,
+                // Could still get any hold duration until the timeout
+                (ScoringMetric::HoldDuration, None) => timeout_at,
+            };
+            deadline = deadline.max(settles_at);
+        }
+        Some(deadline.min(timeout_at))
+    }
+
+    /// Decided, and every player either released, was out of the race or timed out
+    pub fn is_complete(&self) -> bool {
+        if self.decision.is_none() {
+            return false;
+        }
+        if self.go_at.is_none() || self.timed_out {
+            return true;
+        }
+        self.contestants()
+            .all(|p| self.is_out(p) || self.contenders[p.index()].release.is_some())
+    }
+
+    pub fn result(&self) -> Option<RoundResult> {
+        let decision = self.decision?;
+        let since_go = |at: Micros| at.saturating_sub(self.go_at.unwrap_or(at));
+        let timed = decision.timed.map(|p| self.contenders[p.index()]);
+        // Runner-up is the best ranked time among the other players
+        let margin = decision.timed.and_then(|timed| {
+            let own = self.score(timed)?;
+            self.scores()
+                .filter(|&(p, _)| p != timed)
+                .map(|(_, s)| s.saturating_sub(own))
+                .min()
+        });
+
+        Some(RoundResult {
+            verdict: decision.verdict,
+            winner: decision.winner,
+            response_time: decision.timed.and_then(|p| self.score(p)),
+            reaction_time: timed.and_then(|c| c.press).map(since_go),
+            release_time: timed.and_then(|c| c.release).map(since_go),
+            margin,
+            players: PlayerResults::from_fn(self.players, |p| self.player_result(p)),
+        })
+    }
+
+    fn player_result(&self, player: ButtonRole) -> PlayerResult {
+        let contender = self.contenders[player.index()];
+        if self.sitting_out[player.index()] {
+            return PlayerResult::DidNotRace;
+        }
+        match (self.go_at, contender.press, contender.anticipation) {
+            (Some(go_at), Some(press), _) => PlayerResult::Reacted {
+                reaction_time: press.saturating_sub(go_at),
+                release_time: contender.release.map(|at| at.saturating_sub(go_at)),
+                penalized: contender.false_start.is_some(),
+            },
+            (_, _, Some(reaction_time)) => PlayerResult::Anticipated { reaction_time },
+            _ if contender.false_start.is_some() => PlayerResult::FalseStart,
+            (Some(_), None, _) if self.timed_out => PlayerResult::NoPress,
+            _ => PlayerResult::DidNotRace,
+        }
+    }
+
+    pub fn false_started(&self, player: ButtonRole) -> bool {
+        self.contenders[player.index()].false_start.is_some()
+    }
+
+    /// Ranked time of `player` per the scoring metric, penalty included
+    pub fn score(&self, player: ButtonRole) -> Option<Micros> {
+        let go_at = self.go_at?;
+        let contender = self.contenders[player.index()];
+        let time = match self.rules.scoring {
+            ScoringMetric::PressEdge => contender.press?.saturating_sub(go_at),
+            ScoringMetric::Release => contender.release?.saturating_sub(go_at),
+            ScoringMetric::HoldDuration => contender.release?.saturating_sub(contender.press?),
+        };
+        Some(time + self.penalty(player))
+    }
+
+    fn scores(&self) -> impl Iterator<Item = (ButtonRole, Micros)> + '_ {
+        self.contestants()
+            .filter_map(|p| self.score(p).map(|s| (p, s)))
+    }
+
+    fn contestants(&self) -> impl Iterator<Item = ButtonRole> + '_ {
+        ButtonRole::first(self.players).filter(|p| !self.sitting_out[p.index()])
+    }
+
+    fn is_racing(&self, player: ButtonRole) -> bool {
+        player.index() < self.players && !self.sitting_out[player.index()]
+    }
+
+    fn fastest(&self) -> Option<(ButtonRole, Micros)> {
+        self.scores().min_by_key(|&(_, score)| score)
+    }
+
+    fn is_out(&self, player: ButtonRole) -> bool {
+        let out_on_false_start = match self.rules.false_start {
+            FalseStartRule::LoseRound => true,
+            FalseStartRule::PointToOpponent => self.players > 2,
+            FalseStartRule::TimePenalty(_) => false,
+        };
+        out_on_false_start && self.false_started(player)
+    }
+
+    fn penalty(&self, player: ButtonRole) -> Micros {
+        match self.rules.false_start {
+            FalseStartRule::TimePenalty(penalty) if self.false_started(player) => penalty,
+            _ => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Always yields the same value, enough to pick a coin flip side
+    struct FixedCoin(u64);
+
+    impl RandomSource for FixedCoin {
+        fn next_u64(&mut self) -> u64 {
+            self.0
+        }
+    }
+
+    fn round_with(rules: RoundRules) -> Round {
+        Round::new(0, 2, rules, &mut FixedCoin(0))
+    }
+
+    fn with_false_start(false_start: FalseStartRule) -> Round {
+        round_with(RoundRules {
+            false_start,
+            ..Default::default()
+        })
+    }
+
+    fn decided(event: Option<RoundEvent>) -> RoundResult {
+        match event {
+            Some(RoundEvent::Decided(result)) => result,
+            other => panic!("round not decided: {:?}", other),
+        }
+    }
+
+    fn reacted(reaction_time: Micros, release_time: Option<Micros>) -> PlayerResult {
+        PlayerResult::Reacted {
+            reaction_time,
+            release_time,
+            penalized: false,
+        }
+    }
+
+    #[test]
+    fn first_press_after_go_wins_once_tie_window_elapsed() {
+        let mut round = round_with(RoundRules::default());
+        round.go(10_000);
+        assert_eq!(round.on_press(ButtonRole::PLAYER_2, 260_000), None);
+        assert_eq!(round.deadline(), Some(261_000));
+
+        let result = round.poll(261_000).unwrap();
+        assert_eq!(result.verdict, Verdict::Fastest);
+        assert_eq!(result.winner, Some(ButtonRole::PLAYER_2));
+        assert_eq!(result.response_time, Some(250_000));
+        assert_eq!(result.reaction_time, Some(250_000));
+        assert_eq!(result.margin, None);
+    }
+
+    #[test]
+    fn arrival_order_does_not_matter() {
+        let mut round = round_with(RoundRules::default());
+        round.go(0);
+        // Player 1 gets polled first but pressed later than Player 2
+        round.on_press(ButtonRole::PLAYER_1, 120_000);
+        let result = decided(round.on_press(ButtonRole::PLAYER_2, 110_000));
+        assert_eq!(result.winner, Some(ButtonRole::PLAYER_2));
+        assert_eq!(result.margin, Some(10_000));
+    }
+
+    #[test]
+    fn near_simultaneous_presses_follow_tie_policy() {
+        for (policy, coin, winner, reaction) in [
+            (TiePolicy::Draw, 0, None, 200_000),
+            (TiePolicy::Replay, 0, None, 200_000),
+            (TiePolicy::CoinFlip, 0, Some(ButtonRole::PLAYER_1), 200_000),
+            (TiePolicy::CoinFlip, 1, Some(ButtonRole::PLAYER_2), 200_500),
+        ] {
+            let rules = RoundRules {
+                tie_threshold: 1_000,
+                tie_policy: policy,
+                ..Default::default()
+            };
+            let mut round = Round::new(0, 2, rules, &mut FixedCoin(coin));
+            round.go(0);
+            round.on_press(ButtonRole::PLAYER_2, 200_500);
+            let result = decided(round.on_press(ButtonRole::PLAYER_1, 200_000));
+            assert_eq!(result.verdict, Verdict::Tie(policy));
+            assert_eq!(result.winner, winner);
+            assert_eq!(result.reaction_time, Some(reaction));
+        }
+    }
+
+    #[test]
+    fn round_completes_once_everybody_released() {
+        let mut round = round_with(RoundRules::default());
+        round.go(0);
+        round.on_press(ButtonRole::PLAYER_1, 180_000);
+        decided(round.on_press(ButtonRole::PLAYER_2, 240_000));
+        assert!(!round.is_complete());
+        // Decided already, only the round timeout is left to wait for
+        assert_eq!(round.deadline(), Some(3_000_000));
+
+        round.on_release(ButtonRole::PLAYER_2, 300_000);
+        round.on_release(ButtonRole::PLAYER_1, 400_000);
+        assert!(round.is_complete());
+        assert_eq!(round.deadline(), None);
+
+        let result = round.result().unwrap();
+        assert_eq!(result.winner, Some(ButtonRole::PLAYER_1));
+        assert_eq!(result.release_time, Some(400_000));
+        assert_eq!(result.margin, Some(60_000));
+        assert_eq!(
+            *result.players,
+            [
+                reacted(180_000, Some(400_000)),
+                reacted(240_000, Some(300_000))
+            ]
+        );
+    }
+
+    #[test]
+    fn round_times_out_without_opponent_press() {
+        let mut round = round_with(RoundRules::default());
+        round.go(0);
+        round.on_press(ButtonRole::PLAYER_1, 180_000);
+        round.on_release(ButtonRole::PLAYER_1, 250_000);
+        assert_eq!(
+            round.poll(181_000).unwrap().winner,
+            Some(ButtonRole::PLAYER_1)
+        );
+        assert!(!round.is_complete());
+
+        let result = round.poll(3_000_000).unwrap();
+        assert!(round.is_complete());
+        assert_eq!(result.player(ButtonRole::PLAYER_2), PlayerResult::NoPress);
+        assert_eq!(result.margin, None);
+        // Too late to count
+        assert_eq!(round.on_press(ButtonRole::PLAYER_2, 3_100_000), None);
+    }
+
+    #[test]
+    fn nobody_pressing_is_no_reaction() {
+        let mut round = round_with(RoundRules::default());
+        round.go(0);
+        assert_eq!(round.deadline(), Some(3_000_000));
+        let result = round.poll(3_000_000).unwrap();
+        assert_eq!(result.verdict, Verdict::NoReaction);
+        assert_eq!(result.winner, None);
+        assert_eq!(*result.players, [PlayerResult::NoPress; 2]);
+        assert!(round.is_complete());
+    }
+
+    #[test]
+    fn false_start_gives_point_to_opponent() {
+        let mut round = with_false_start(FalseStartRule::PointToOpponent);
+        let event = round.on_press(ButtonRole::PLAYER_1, 500);
+        assert_eq!(
+            event,
+            Some(RoundEvent::FalseStart {
+                player: ButtonRole::PLAYER_1,
+                at: 500
+            })
+        );
+        let result = round.result().unwrap();
+        assert_eq!(result.verdict, Verdict::FalseStart);
+        assert_eq!(result.winner, Some(ButtonRole::PLAYER_2));
+        assert_eq!(result.response_time, None);
+        assert_eq!(
+            *result.players,
+            [PlayerResult::FalseStart, PlayerResult::DidNotRace]
+        );
+        assert!(round.is_complete());
+    }
+
+    #[test]
+    fn false_start_loses_the_round_for_offender_only() {
+        let mut round = with_false_start(FalseStartRule::LoseRound);
+        round.on_press(ButtonRole::PLAYER_1, 500);
+        assert!(round.result().is_none());
+
+        // Early presser is ignored once GO was given, opponent still has to react
+        round.go(1_000_000);
+        assert_eq!(round.on_press(ButtonRole::PLAYER_1, 1_200_000), None);
+        let result = decided(round.on_press(ButtonRole::PLAYER_2, 1_400_000));
+        assert_eq!(result.winner, Some(ButtonRole::PLAYER_2));
+        assert_eq!(result.response_time, Some(400_000));
+        assert_eq!(
+            result.player(ButtonRole::PLAYER_1),
+            PlayerResult::FalseStart
+        );
+    }
+
+    #[test]
+    fn too_fast_after_go_is_an_anticipation() {
+        let mut round = with_false_start(FalseStartRule::LoseRound);
+        round.go(1_000_000);
+        assert_eq!(
+            round.on_press(ButtonRole::PLAYER_1, 1_040_000),
+            Some(RoundEvent::Anticipated {
+                player: ButtonRole::PLAYER_1,
+                reaction_time: 40_000
+            })
+        );
+        // Out like a false start, pressing again changes nothing
+        assert_eq!(round.on_press(ButtonRole::PLAYER_1, 1_150_000), None);
+        let result = decided(round.on_press(ButtonRole::PLAYER_2, 1_300_000));
+        assert_eq!(result.winner, Some(ButtonRole::PLAYER_2));
+        assert_eq!(
+            result.player(ButtonRole::PLAYER_1),
+            PlayerResult::Anticipated {
+                reaction_time: 40_000
+            }
+        );
+        assert_eq!(result.player(ButtonRole::PLAYER_1).reaction_time(), None);
+    }
+
+    #[test]
+    fn press_before_go_delivered_after_it_is_a_false_start() {
+        let mut round = with_false_start(FalseStartRule::LoseRound);
+        round.go(1_000_000);
+        assert_eq!(
+            round.on_press(ButtonRole::PLAYER_1, 990_000),
+            Some(RoundEvent::FalseStart {
+                player: ButtonRole::PLAYER_1,
+                at: 990_000
+            })
+        );
+        let result = decided(round.on_press(ButtonRole::PLAYER_2, 1_250_000));
+        assert_eq!(result.winner, Some(ButtonRole::PLAYER_2));
+        assert_eq!(
+            result.player(ButtonRole::PLAYER_1),
+            PlayerResult::FalseStart
+        );
+    }
+
+    #[test]
+    fn anticipation_follows_the_false_start_rule() {
+        let mut round = with_false_start(FalseStartRule::PointToOpponent);
+        round.go(0);
+        round.on_press(ButtonRole::PLAYER_2, 99_999);
+        let result = round.result().unwrap();
+        assert_eq!(result.verdict, Verdict::FalseStart);
+        assert_eq!(result.winner, Some(ButtonRole::PLAYER_1));
+
+        // With a penalty the player has to press again, slower than the minimum
+        let mut round = with_false_start(FalseStartRule::TimePenalty(100_000));
+        round.go(0);
+        round.on_press(ButtonRole::PLAYER_1, 20_000);
+        assert_eq!(round.on_press(ButtonRole::PLAYER_1, 50_000), None);
+        round.on_press(ButtonRole::PLAYER_1, 120_000);
+        let result = decided(round.on_press(ButtonRole::PLAYER_2, 260_000));
+        assert_eq!(result.winner, Some(ButtonRole::PLAYER_1));
+        assert_eq!(result.response_time, Some(220_000));
+        assert_eq!(result.margin, Some(40_000));
+    }
+
+    #[test]
+    fn everyone_false_starting_voids_the_round() {
+        let mut round = with_false_start(FalseStartRule::LoseRound);
+        round.on_press(ButtonRole::PLAYER_1, 500);
+        assert_eq!(round.on_press(ButtonRole::PLAYER_1, 600), None);
+        round.on_press(ButtonRole::PLAYER_2, 700);
+        let result = round.result().unwrap();
+        assert_eq!(result.verdict, Verdict::Void);
+        assert_eq!(result.winner, None);
+        assert_eq!(*result.players, [PlayerResult::FalseStart; 2]);
+    }
+
+    #[test]
+    fn time_penalty_delays_decision_until_opponent_cannot_win() {
+        let mut round = round_with(RoundRules {
+            false_start: FalseStartRule::TimePenalty(200_000),
+            tie_threshold: 0,
+            ..Default::default()
+        });
+        round.on_press(ButtonRole::PLAYER_1, 500);
+        round.go(1_000_000);
+
+        // Penalized 150 + 200 = 350 ms, the opponent may still beat it until GO + 350 ms
+        assert_eq!(round.on_press(ButtonRole::PLAYER_1, 1_150_000), None);
+        assert_eq!(round.deadline(), Some(1_350_000));
+        assert_eq!(round.poll(1_300_000), None);
+
+        let result = decided(round.on_press(ButtonRole::PLAYER_2, 1_320_000));
+        assert_eq!(result.winner, Some(ButtonRole::PLAYER_2));
+        assert_eq!(result.margin, Some(30_000));
+        assert_eq!(
+            result.player(ButtonRole::PLAYER_1),
+            PlayerResult::Reacted {
+                reaction_time: 150_000,
+                release_time: None,
+                penalized: true
+            }
+        );
+    }
+
+    #[test]
+    fn time_penalized_press_wins_when_unchallenged() {
+        let mut round = with_false_start(FalseStartRule::TimePenalty(200_000));
+        round.on_press(ButtonRole::PLAYER_2, 500);
+        round.go(1_000_000);
+        round.on_press(ButtonRole::PLAYER_2, 1_100_000);
+        assert_eq!(round.poll(1_300_000), None);
+        let result = round.poll(1_301_000).unwrap();
+        assert_eq!(result.winner, Some(ButtonRole::PLAYER_2));
+        assert_eq!(result.response_time, Some(300_000));
+        assert_eq!(result.reaction_time, Some(100_000));
+    }
+
+    #[test]
+    fn long_hold_does_not_slow_down_press_edge_reaction() {
+        let mut round = round_with(RoundRules::default());
+        round.go(0);
+        round.on_press(ButtonRole::PLAYER_1, 180_000);
+        round.on_release(ButtonRole::PLAYER_1, 900_000);
+        round.on_press(ButtonRole::PLAYER_2, 220_000);
+        round.on_release(ButtonRole::PLAYER_2, 260_000);
+        let result = round.result().unwrap();
+        assert_eq!(result.winner, Some(ButtonRole::PLAYER_1));
+        assert_eq!(result.response_time, Some(180_000));
+    }
+
+    #[test]
+    fn release_metric_waits_for_releases() {
+        let mut round = round_with(RoundRules {
+            scoring: ScoringMetric::Release,
+            ..Default::default()
+        });
+        round.go(0);
+        round.on_press(ButtonRole::PLAYER_1, 180_000);
+        round.on_press(ButtonRole::PLAYER_2, 220_000);
+        round.on_release(ButtonRole::PLAYER_1, 600_000);
+        assert_eq!(round.deadline(), Some(601_000));
+        let result = decided(round.on_release(ButtonRole::PLAYER_2, 300_000));
+        assert_eq!(result.winner, Some(ButtonRole::PLAYER_2));
+        assert_eq!(result.response_time, Some(300_000));
+        assert_eq!(result.reaction_time, Some(220_000));
+        assert_eq!(result.release_time, Some(300_000));
+    }
+
+    #[test]
+    fn hold_duration_metric_waits_for_everyone_to_press() {
+        let mut round = round_with(RoundRules {
+            scoring: ScoringMetric::HoldDuration,
+            ..Default::default()
+        });
+        round.go(0);
+        round.on_press(ButtonRole::PLAYER_1, 200_000);
+        round.on_release(ButtonRole::PLAYER_1, 260_000);
+        // Player 2 could still produce any hold duration until the timeout
+        assert_eq!(round.deadline(), Some(3_000_000));
+        assert_eq!(round.poll(1_000_000), None);
+
+        round.on_press(ButtonRole::PLAYER_2, 500_000);
+        assert_eq!(round.deadline(), Some(561_000));
+        let result = round.poll(561_000).unwrap();
+        assert_eq!(result.winner, Some(ButtonRole::PLAYER_1));
+        assert_eq!(result.response_time, Some(60_000));
+    }
+
+    #[test]
+    fn four_players_race_and_the_rest_are_ignored() {
+        let mut round = Round::new(0, 4, RoundRules::default(), &mut FixedCoin(0));
+        round.go(0);
+        let [p1, _, p3, p4, p5, ..] = ButtonRole::ALL;
+        assert_eq!(round.on_press(p5, 100_000), None);
+        assert_eq!(round.on_press(p3, 190_000), None);
+        // Past the tie window of the fastest, nobody can catch up anymore
+        let result = decided(round.on_press(p4, 200_000));
+        assert_eq!(result.winner, Some(p3));
+        assert_eq!(result.margin, Some(10_000));
+        assert_eq!(round.on_press(p1, 240_000), None);
+        assert_eq!(result.players.len(), 4);
+    }
+
+    #[test]
+    fn point_to_opponent_only_knocks_out_with_more_players() {
+        let rules = RoundRules {
+            false_start: FalseStartRule::PointToOpponent,
+            ..Default::default()
+        };
+        let mut round = Round::new(0, 3, rules, &mut FixedCoin(0));
+        let [p1, p2, p3, ..] = ButtonRole::ALL;
+        round.on_press(p2, 500);
+        assert!(round.result().is_none());
+        round.go(1_000);
+        assert_eq!(round.on_press(p2, 1_100), None);
+        round.on_press(p1, 200_000);
+        let result = decided(round.on_press(p3, 250_000));
+        assert_eq!(result.winner, Some(p1));
+        assert_eq!(result.player(p2), PlayerResult::FalseStart);
+    }
+}
@@ -0,0 +1,237 @@
+use crate::Micros;
+
+/// Phases of the game loop driven by the firmware
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum GameState {
+    Waiting,
+    Playing,
+    ComputingResults,
+    Finished,
+    /// Solo practice session, entered from Waiting and back to it once over
+    Practicing,
+}
+
+impl GameState {
+    pub const ALL: [GameState; 5] = [
+        GameState::Waiting,
+        GameState::Playing,
+        GameState::ComputingResults,
+        GameState::Finished,
+        GameState::Practicing,
+    ];
+
+    /// Every legal `(from, to)` move, anything else is refused
+    pub const TRANSITIONS: [(GameState, GameState); 8] = [
+        (GameState::Waiting, GameState::Playing),
+        (GameState::Playing, GameState::ComputingResults),
+        (GameState::ComputingResults, GameState::Finished),
+        (GameState::Finished, GameState::Waiting),
+        (GameState::Waiting, GameState::Practicing),
+        // Session over or aborted by a soft reset alike
+        (GameState::Practicing, GameState::Waiting),
+        // Soft reset aborting a game in progress
+        (GameState::Playing, GameState::Waiting),
+        (GameState::ComputingResults, GameState::Waiting),
+    ];
+
+    pub fn can_transition_to(self, next: GameState) -> bool {
+        Self::TRANSITIONS.contains(&(self, next))
+    }
+}
+
+/// Why a transition was refused, the state is left unchanged
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TransitionError {
+    /// Already in the requested state
+    AlreadyIn(GameState),
+    /// Not in [`GameState::TRANSITIONS`]
+    Illegal { from: GameState, to: GameState },
+}
+
+/// Called around every accepted transition, exit before entry
+pub trait StateHooks {
+    /// Leaving `state` after `duration` in it
+    fn on_exit(&mut self, _state: GameState, _duration: Micros) {}
+    /// Entering `state` at `now`
+    fn on_enter(&mut self, _state: GameState, _now: Micros) {}
+}
+
+/// Hooks that do nothing
+impl StateHooks for () {}
+
+/// Current [`GameState`] and when it was entered, only moves along the legal graph
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct StateMachine {
+    state: GameState,
+    state_start: Micros,
+}
+
+impl StateMachine {
+    /// Start in [`GameState::Waiting`] at `now`
+    pub const fn new(now: Micros) -> Self {
+        Self {
+            state: GameState::Waiting,
+            state_start: now,
+        }
+    }
+
+    pub fn state(&self) -> GameState {
+        self.state
+    }
+
+    pub fn state_start(&self) -> Micros {
+        self.state_start
+    }
+
+    /// Time spent in the current state so far
+    pub fn state_duration(&self, now: Micros) -> Micros {
+        now.saturating_sub(self.state_start)
+    }
+
+    /// Move to `next` if the table allows it, running the exit then entry hooks
+    ///
+    /// Returns the time spent in the state that was left.
+    pub fn transition(
+        &mut self,
+        next: GameState,
+        now: Micros,
+        hooks: &mut impl StateHooks,
+    ) -> Result<Micros, TransitionError> {
+        if next == self.state {
+            return Err(TransitionError::AlreadyIn(next));
+        }
+        if !self.state.can_transition_to(next) {
+            return Err(TransitionError::Illegal {
+                from: self.state,
+                to: next,
+            });
+        }
+        let duration = self.state_duration(now);
+        hooks.on_exit(self.state, duration);
+        self.state = next;
+        self.state_start = now;
+        hooks.on_enter(next, now);
+        Ok(duration)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use std::vec::Vec;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Recorder(Vec<(&'static str, GameState, Micros)>);
+
+    impl StateHooks for Recorder {
+        fn on_exit(&mut self, state: GameState, duration: Micros) {
+            self.0.push(("exit", state, duration));
+        }
+
+        fn on_enter(&mut self, state: GameState, now: Micros) {
+            self.0.push(("enter", state, now));
+        }
+    }
+
+    fn machine_in(state: GameState) -> StateMachine {
+        StateMachine {
+            state,
+            state_start: 0,
+        }
+    }
+
+    #[test]
+    fn every_pair_follows_the_table() {
+        use GameState::*;
+        let legal = [
+            (Waiting, Playing),
+            (Playing, ComputingResults),
+            (ComputingResults, Finished),
+            (Finished, Waiting),
+            (Waiting, Practicing),
+            (Practicing, Waiting),
+            (Playing, Waiting),
+            (ComputingResults, Waiting),
+        ];
+        for from in GameState::ALL {
+            for to in GameState::ALL {
+                let mut machine = machine_in(from);
+                let mut hooks = Recorder::default();
+                let result = machine.transition(to, 10, &mut hooks);
+                if from == to {
+                    assert_eq!(result, Err(TransitionError::AlreadyIn(to)));
+                } else if legal.contains(&(from, to)) {
+                    assert_eq!(result, Ok(10), "{from:?} -> {to:?}");
+                    assert_eq!(machine.state(), to);
+                    continue;
+                } else {
+                    assert_eq!(result, Err(TransitionError::Illegal { from, to }));
+                }
+                assert_eq!(machine.state(), from, "{from:?} -> {to:?}");
+                assert!(hooks.0.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn every_state_is_reachable_and_can_be_left() {
+        for state in GameState::ALL {
+            assert!(GameState::ALL.iter().any(|s| s.can_transition_to(state)));
+            assert!(GameState::ALL.iter().any(|s| state.can_transition_to(*s)));
+        }
+    }
+
+    #[test]
+    fn full_cycle_runs_hooks_in_order() {
+        let mut machine = StateMachine::new(100);
+        let mut hooks = Recorder::default();
+        let cycle = [
+            GameState::Playing,
+            GameState::ComputingResults,
+            GameState::Finished,
+            GameState::Waiting,
+        ];
+        for (i, next) in cycle.into_iter().enumerate() {
+            let now = 1_000 * (i as Micros + 1);
+            machine.transition(next, now, &mut hooks).unwrap();
+        }
+        assert_eq!(machine.state(), GameState::Waiting);
+        assert_eq!(machine.state_start(), 4_000);
+        assert_eq!(
+            hooks.0[..4],
+            [
+                ("exit", GameState::Waiting, 900),
+                ("enter", GameState::Playing, 1_000),
+                ("exit", GameState::Playing, 1_000),
+                ("enter", GameState::ComputingResults, 2_000),
+            ]
+        );
+        assert_eq!(hooks.0.len(), 8);
+    }
+
+    #[test]
+    fn every_state_can_abort_to_waiting() {
+        for state in GameState::ALL {
+            let mut machine = machine_in(state);
+            let result = machine.transition(GameState::Waiting, 0, &mut ());
+            assert!(result.is_ok() || state == GameState::Waiting);
+            assert_eq!(machine.state(), GameState::Waiting);
+        }
+    }
+
+    #[test]
+    fn duration_counts_from_entry() {
+        let mut machine = StateMachine::new(500);
+        assert_eq!(machine.state_duration(200), 0);
+        assert_eq!(machine.state_duration(1_500), 1_000);
+        machine
+            .transition(GameState::Playing, 2_000, &mut ())
+            .unwrap();
+        assert_eq!(machine.state_duration(2_250), 250);
+    }
+}
@@ -0,0 +1,242 @@
+use heapless::Vec;
+
+use crate::{ButtonRole, Micros, PlayerResult, RoundResult, MAX_PLAYERS};
+
+/// Most reaction times kept per player, one per round of the longest match
+pub const MAX_SAMPLES: usize = crate::MAX_ROUNDS;
+
+/// Summary of a set of reaction times, integer math only
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ReactionStats {
+    pub count: usize,
+    pub mean: Micros,
+    pub median: Micros,
+    pub min: Micros,
+    pub max: Micros,
+    /// Population standard deviation
+    pub std_dev: Micros,
+    /// 1000 minus the coefficient of variation in per mille, 1000 being perfectly steady
+    pub consistency: u16,
+}
+
+impl ReactionStats {
+    /// `None` without any sample, samples past [`MAX_SAMPLES`] are ignored
+    pub fn from_samples(samples: impl IntoIterator<Item = Micros>) -> Option<Self> {
+        let mut sorted: Vec<Micros, MAX_SAMPLES> = samples.into_iter().take(MAX_SAMPLES).collect();
+        if sorted.is_empty() {
+            return None;
+        }
+        sorted.sort_unstable();
+
+        let count = sorted.len();
+        let n = count as u64;
+        let mean = sorted.iter().sum::<u64>() / n;
+        let median = match count % 2 {
+            1 => sorted[count / 2],
+            _ => (sorted[count / 2 - 1] + sorted[count / 2]) / 2,
+        };
+        let variance = sorted
+            .iter()
+            .map(|x| {
+                let diff = x.abs_diff(mean);
+                diff * diff
+            })
+            .sum::<u64>()
+            / n;
+        let std_dev = variance.isqrt();
+        let cv_per_mille = std_dev.saturating_mul(1000).checked_div(mean).unwrap_or(0);
+
+        Some(Self {
+            count,
+            mean,
+            median,
+            min: sorted[0],
+            max: sorted[count - 1],
+            std_dev,
+            consistency: 1000u64.saturating_sub(cv_per_mille) as u16,
+        })
+    }
+}
+
+/// Everything a player did over a match
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PlayerStats {
+    pub player: ButtonRole,
+    pub wins: usize,
+    pub false_starts: usize,
+    /// Presses after GO faster than the minimum reaction, not in `false_starts`
+    pub anticipations: usize,
+    pub no_presses: usize,
+    /// Over every round the player pressed in after GO, won or lost, anticipations left out
+    pub reaction: Option<ReactionStats>,
+    /// Average lead over the runner-up in rounds won where both pressed
+    pub avg_margin: Option<Micros>,
+}
+
+impl PlayerStats {
+    pub fn from_rounds(player: ButtonRole, rounds: &[RoundResult]) -> Self {
+        let mut stats = Self {
+            player,
+            wins: 0,
+            false_starts: 0,
+            anticipations: 0,
+            no_presses: 0,
+            reaction: None,
+            avg_margin: None,
+        };
+        let (mut margins, mut margin_total) = (0, 0);
+        for round in rounds {
+            match round.player(player) {
+                PlayerResult::Reacted { penalized, .. } => stats.false_starts += penalized as usize,
+                PlayerResult::FalseStart => stats.false_starts += 1,
+                PlayerResult::Anticipated { .. } => stats.anticipations += 1,
+                PlayerResult::NoPress => stats.no_presses += 1,
+                PlayerResult::DidNotRace => {}
+            }
+            if round.winner == Some(player) {
+                stats.wins += 1;
+                if let Some(margin) = round.margin {
+                    margins += 1;
+                    margin_total += margin;
+                }
+            }
+        }
+        stats.reaction = ReactionStats::from_samples(
+            rounds
+                .iter()
+                .filter_map(|r| r.player(player).reaction_time()),
+        );
+        stats.avg_margin = (margins > 0).then(|| margin_total / margins);
+        stats
+    }
+}
+
+/// End-of-game stats for every player, as computed in ComputingResults
+#[derive(PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct MatchStats {
+    pub rounds_played: usize,
+    /// Indexed by [`ButtonRole::index`]
+    pub players: Vec<PlayerStats, MAX_PLAYERS>,
+}
+
+impl MatchStats {
+    /// Stats of the first `players` players
+    pub fn from_rounds(players: usize, rounds: &[RoundResult]) -> Self {
+        Self {
+            rounds_played: rounds.len(),
+            players: ButtonRole::first(players)
+                .map(|p| PlayerStats::from_rounds(p, rounds))
+                .collect(),
+        }
+    }
+
+    pub fn player(&self, player: ButtonRole) -> &PlayerStats {
+        &self.players[player.index()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PlayerResults, Verdict};
+
+    fn reacted(reaction_time: Micros) -> PlayerResult {
+        PlayerResult::Reacted {
+            reaction_time,
+            release_time: None,
+            penalized: false,
+        }
+    }
+
+    fn round(
+        winner: ButtonRole,
+        players: [PlayerResult; 2],
+        margin: Option<Micros>,
+    ) -> RoundResult {
+        let players = PlayerResults::from(players);
+        let winner_result = players[winner.index()];
+        RoundResult {
+            verdict: Verdict::Fastest,
+            winner: Some(winner),
+            response_time: winner_result.reaction_time(),
+            reaction_time: winner_result.reaction_time(),
+            release_time: None,
+            margin,
+            players,
+        }
+    }
+
+    #[test]
+    fn summary_of_reaction_times() {
+        let stats =
+            ReactionStats::from_samples([260_000, 200_000, 280_000, 240_000, 220_000]).unwrap();
+        assert_eq!(stats.count, 5);
+        assert_eq!(stats.mean, 240_000);
+        assert_eq!(stats.median, 240_000);
+        assert_eq!(stats.min, 200_000);
+        assert_eq!(stats.max, 280_000);
+        // sqrt(800) ms
+        assert_eq!(stats.std_dev, 28_284);
+        assert_eq!(stats.consistency, 883);
+    }
+
+    #[test]
+    fn even_count_median_and_steady_player() {
+        let stats = ReactionStats::from_samples([300_000, 200_000]).unwrap();
+        assert_eq!(stats.median, 250_000);
+
+        let steady = ReactionStats::from_samples([210_000; 4]).unwrap();
+        assert_eq!(steady.std_dev, 0);
+        assert_eq!(steady.consistency, 1000);
+        assert_eq!(ReactionStats::from_samples([]), None);
+    }
+
+    #[test]
+    fn every_player_gets_stats_from_every_round() {
+        let rounds = [
+            round(
+                ButtonRole::PLAYER_1,
+                [reacted(200), reacted(300)],
+                Some(100),
+            ),
+            round(
+                ButtonRole::PLAYER_2,
+                [PlayerResult::FalseStart, reacted(240)],
+                None,
+            ),
+            round(
+                ButtonRole::PLAYER_1,
+                [reacted(220), PlayerResult::NoPress],
+                None,
+            ),
+            round(ButtonRole::PLAYER_1, [reacted(240), reacted(260)], Some(20)),
+            round(
+                ButtonRole::PLAYER_1,
+                [
+                    reacted(250),
+                    PlayerResult::Anticipated { reaction_time: 30 },
+                ],
+                None,
+            ),
+        ];
+        let stats = MatchStats::from_rounds(2, &rounds);
+        assert_eq!(stats.rounds_played, 5);
+
+        let p1 = stats.player(ButtonRole::PLAYER_1);
+        assert_eq!((p1.wins, p1.false_starts, p1.no_presses), (4, 1, 0));
+        assert_eq!(p1.reaction.unwrap().count, 4);
+        assert_eq!(p1.reaction.unwrap().mean, 227);
+        assert_eq!(p1.avg_margin, Some(60));
+
+        let p2 = stats.player(ButtonRole::PLAYER_2);
+        assert_eq!((p2.wins, p2.false_starts, p2.no_presses), (1, 0, 1));
+        assert_eq!(p2.anticipations, 1);
+        // The anticipation is nowhere near the best time
+        assert_eq!(p2.reaction.unwrap().mean, 266);
+        assert_eq!(p2.reaction.unwrap().min, 240);
+        assert_eq!(p2.avg_margin, None);
+    }
+}
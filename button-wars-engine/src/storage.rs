@@ -0,0 +1,514 @@
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::crc::crc32;
+
+/// What a value of the [`Store`] holds, one key per kind of record
+///
+/// Every record is CRC checked here, values only carry a magic telling their layout apart.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u16)]
+pub enum Key {
+    Calibration = 1,
+    Records = 2,
+    Config = 3,
+}
+
+/// Largest value the store takes, in bytes
+pub const MAX_VALUE: usize = 128;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum StoreError<E> {
+    Flash(E),
+    /// Value over [`MAX_VALUE`], or over the buffer it's read into
+    TooLarge,
+    /// The latest values don't fit in a sector anymore
+    Full,
+}
+
+impl<E> From<E> for StoreError<E> {
+    fn from(error: E) -> Self {
+        Self::Flash(error)
+    }
+}
+
+// Records and sector headers start on this alignment, a multiple of any write size we support
+const ALIGN: usize = 4;
+const SECTOR_MAGIC: [u8; 4] = *b"BWKV";
+// Magic, generation and CRC
+const SECTOR_HEADER: u32 = 12;
+// CRC of the rest, key and length
+const RECORD_HEADER: usize = 8;
+const ERASED_KEY: u16 = 0xFFFF;
+
+// Header and value rounded up to the alignment
+const fn record_size(len: usize) -> u32 {
+    (RECORD_HEADER + len.next_multiple_of(ALIGN)) as u32
+}
+
+// What the log holds at some offset of a sector
+enum Slot {
+    Record {
+        key: u16,
+        len: usize,
+        next: u32,
+    },
+    /// Erased, or no room left for a header
+    Free,
+    /// Cut short by a reset or corrupted, the log ends there
+    Torn,
+}
+
+/// Key/value log over a few flash sectors, CRC checked and wear leveled
+///
+/// Writes append to the active sector, when it's full the latest values move to the next
+/// sector in turn, so every sector wears alike. A sector only counts once its header is
+/// written, after the values it took, and a torn record just ends the log: a reset in the
+/// middle of any write keeps the previous values.
+pub struct Store<F> {
+    flash: F,
+    base: u32,
+    sectors: u32,
+    active: u32,
+    generation: u32,
+    /// Where the next record goes in the active sector
+    end: u32,
+    /// The log ends on a torn record, the next write moves past it
+    torn: bool,
+}
+
+impl<F: NorFlash> Store<F> {
+    /// Store over `sectors` erase sectors from `base`, formatted if none holds a log
+    pub fn mount(flash: F, base: u32, sectors: u32) -> Result<Self, StoreError<F::Error>> {
+        assert!(sectors >= 2 && base % F::ERASE_SIZE as u32 == 0);
+        assert!(ALIGN % F::WRITE_SIZE == 0 && ALIGN % F::READ_SIZE == 0);
+        assert!(record_size(MAX_VALUE) + SECTOR_HEADER <= F::ERASE_SIZE as u32);
+
+        let mut store = Self {
+            flash,
+            base,
+            sectors,
+            active: 0,
+            generation: 0,
+            end: SECTOR_HEADER,
+            torn: false,
+        };
+        let mut newest = None;
+        for sector in 0..sectors {
+            if let Some(generation) = store.generation_of(sector)? {
+                if newest.is_none_or(|(_, newest)| generation > newest) {
+                    newest = Some((sector, generation));
+                }
+            }
+        }
+        match newest {
+            Some((sector, generation)) => {
+                store.active = sector;
+                store.generation = generation;
+                let mut value = [0; MAX_VALUE];
+                loop {
+                    match store.slot(sector, store.end, &mut value)? {
+                        Slot::Record { next, .. } => store.end = next,
+                        Slot::Free => break,
+                        Slot::Torn => {
+                            store.torn = true;
+                            break;
+                        }
+                    }
+                }
+            }
+            None => {
+                store.erase(0)?;
+                store.write_header(0, 0)?;
+            }
+        }
+        Ok(store)
+    }
+
+    /// Latest value of `key` copied into `buf`, its length or `None` if never written
+    pub fn read(
+        &mut self,
+        key: Key,
+        buf: &mut [u8],
+    ) -> Result<Option<usize>, StoreError<F::Error>> {
+        let mut value = [0; MAX_VALUE];
+        let mut found = None;
+        let mut offset = SECTOR_HEADER;
+        while let Slot::Record { key: k, len, next } = self.slot(self.active, offset, &mut value)? {
+            if k == key as u16 {
+                buf.get_mut(..len)
+                    .ok_or(StoreError::TooLarge)?
+                    .copy_from_slice(&value[..len]);
+                found = Some(len);
+            }
+            offset = next;
+        }
+        Ok(found)
+    }
+
+    /// `value` becomes the latest one of `key`
+    pub fn write(&mut self, key: Key, value: &[u8]) -> Result<(), StoreError<F::Error>> {
+        if value.len() > MAX_VALUE {
+            return Err(StoreError::TooLarge);
+        }
+        if self.torn || self.end + record_size(value.len()) > F::ERASE_SIZE as u32 {
+            return self.compact(key, value);
+        }
+        self.write_record(self.active, self.end, key as u16, value)?;
+        self.end += record_size(value.len());
+        Ok(())
+    }
+
+    // Latest values of the other keys then `value` into the next sector, which only takes
+    // over once its header is written
+    fn compact(&mut self, key: Key, value: &[u8]) -> Result<(), StoreError<F::Error>> {
+        let target = (self.active + 1) % self.sectors;
+        self.erase(target)?;
+
+        let mut end = SECTOR_HEADER;
+        let mut kept = [0; MAX_VALUE];
+        let mut offset = SECTOR_HEADER;
+        while let Slot::Record { key: k, len, next } = self.slot(self.active, offset, &mut kept)? {
+            if k != key as u16 && self.is_latest(k, next)? {
+                if end + record_size(len) > F::ERASE_SIZE as u32 {
+                    return Err(StoreError::Full);
+                }
+                self.write_record(target, end, k, &kept[..len])?;
+                end += record_size(len);
+            }
+            offset = next;
+        }
+        if end + record_size(value.len()) > F::ERASE_SIZE as u32 {
+            return Err(StoreError::Full);
+        }
+        self.write_record(target, end, key as u16, value)?;
+        end += record_size(value.len());
+
+        self.write_header(target, self.generation.wrapping_add(1))?;
+        self.active = target;
+        self.generation = self.generation.wrapping_add(1);
+        self.end = end;
+        self.torn = false;
+        Ok(())
+    }
+
+    // No valid record of `key` from `offset` on in the active sector
+    fn is_latest(&mut self, key: u16, mut offset: u32) -> Result<bool, F::Error> {
+        let mut value = [0; MAX_VALUE];
+        while let Slot::Record { key: k, next, .. } = self.slot(self.active, offset, &mut value)? {
+            if k == key {
+                return Ok(false);
+            }
+            offset = next;
+        }
+        Ok(true)
+    }
+
+    fn address(&self, sector: u32, offset: u32) -> u32 {
+        self.base + sector * F::ERASE_SIZE as u32 + offset
+    }
+
+    fn erase(&mut self, sector: u32) -> Result<(), F::Error> {
+        let from = self.address(sector, 0);
+        self.flash.erase(from, from + F::ERASE_SIZE as u32)
+    }
+
+    // Generation of a sector holding a log, `None` for an erased, unfinished or foreign one
+    fn generation_of(&mut self, sector: u32) -> Result<Option<u32>, F::Error> {
+        let mut header = [0; SECTOR_HEADER as usize];
+        self.flash.read(self.address(sector, 0), &mut header)?;
+        let crc = u32::from_le_bytes([header[8], header[9], header[10], header[11]]);
+        if header[..4] != SECTOR_MAGIC || crc32(&header[..8]) != crc {
+            return Ok(None);
+        }
+        Ok(Some(u32::from_le_bytes([
+            header[4], header[5], header[6], header[7],
+        ])))
+    }
+
+    fn write_header(&mut self, sector: u32, generation: u32) -> Result<(), F::Error> {
+        let mut header = [0; SECTOR_HEADER as usize];
+        header[..4].copy_from_slice(&SECTOR_MAGIC);
+        header[4..8].copy_from_slice(&generation.to_le_bytes());
+        let crc = crc32(&header[..8]);
+        header[8..].copy_from_slice(&crc.to_le_bytes());
+        self.flash.write(self.address(sector, 0), &header)
+    }
+
+    // The value of a record is left in `value`
+    fn slot(
+        &mut self,
+        sector: u32,
+        offset: u32,
+        value: &mut [u8; MAX_VALUE],
+    ) -> Result<Slot, F::Error> {
+        let sector_size = F::ERASE_SIZE as u32;
+        if offset + RECORD_HEADER as u32 > sector_size {
+            return Ok(Slot::Free);
+        }
+        let mut record = [0; RECORD_HEADER + MAX_VALUE];
+        self.flash
+            .read(self.address(sector, offset), &mut record[..RECORD_HEADER])?;
+        if record[..RECORD_HEADER] == [0xFF; RECORD_HEADER] {
+            return Ok(Slot::Free);
+        }
+        let crc = u32::from_le_bytes([record[0], record[1], record[2], record[3]]);
+        let key = u16::from_le_bytes([record[4], record[5]]);
+        let len = u16::from_le_bytes([record[6], record[7]]) as usize;
+        if key == ERASED_KEY || len > MAX_VALUE || offset + record_size(len) > sector_size {
+            return Ok(Slot::Torn);
+        }
+        self.flash.read(
+            self.address(sector, offset + RECORD_HEADER as u32),
+            &mut record[RECORD_HEADER..RECORD_HEADER + len.next_multiple_of(ALIGN)],
+        )?;
+        if crc32(&record[4..RECORD_HEADER + len]) != crc {
+            return Ok(Slot::Torn);
+        }
+        value[..len].copy_from_slice(&record[RECORD_HEADER..RECORD_HEADER + len]);
+        Ok(Slot::Record {
+            key,
+            len,
+            next: offset + record_size(len),
+        })
+    }
+
+    // Header and value in one write, the padding left erased
+    fn write_record(
+        &mut self,
+        sector: u32,
+        offset: u32,
+        key: u16,
+        value: &[u8],
+    ) -> Result<(), F::Error> {
+        let mut record = [0xFF; RECORD_HEADER + MAX_VALUE];
+        record[4..6].copy_from_slice(&key.to_le_bytes());
+        record[6..8].copy_from_slice(&(value.len() as u16).to_le_bytes());
+        record[RECORD_HEADER..RECORD_HEADER + value.len()].copy_from_slice(value);
+        let crc = crc32(&record[4..RECORD_HEADER + value.len()]);
+        record[..4].copy_from_slice(&crc.to_le_bytes());
+        let size = record_size(value.len()) as usize;
+        self.flash
+            .write(self.address(sector, offset), &record[..size])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use std::vec::Vec;
+
+    use embedded_storage::nor_flash::{ErrorType, NorFlashErrorKind, ReadNorFlash};
+
+    use super::*;
+
+    const SECTOR: usize = 256;
+
+    // NOR flash in RAM: writes only clear bits, and power can be cut after some bytes
+    struct MockFlash {
+        bytes: Vec<u8>,
+        erases: Vec<u32>,
+        /// Bytes still written before the power cut, `None` for no cut
+        power: Option<usize>,
+    }
+
+    impl MockFlash {
+        fn new(sectors: usize) -> Self {
+            Self {
+                bytes: std::vec![0xFF; sectors * SECTOR],
+                erases: std::vec![0; sectors],
+                power: None,
+            }
+        }
+
+        // Spend the power budget, false once it's gone
+        fn powered(&mut self) -> bool {
+            match &mut self.power {
+                Some(0) => false,
+                Some(left) => {
+                    *left -= 1;
+                    true
+                }
+                None => true,
+            }
+        }
+    }
+
+    impl ErrorType for MockFlash {
+        type Error = NorFlashErrorKind;
+    }
+
+    impl ReadNorFlash for MockFlash {
+        const READ_SIZE: usize = 1;
+
+        fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+            let from = offset as usize;
+            let source = self
+                .bytes
+                .get(from..from + bytes.len())
+                .ok_or(NorFlashErrorKind::OutOfBounds)?;
+            bytes.copy_from_slice(source);
+            Ok(())
+        }
+
+        fn capacity(&self) -> usize {
+            self.bytes.len()
+        }
+    }
+
+    impl NorFlash for MockFlash {
+        const WRITE_SIZE: usize = 4;
+        const ERASE_SIZE: usize = SECTOR;
+
+        fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+            assert_eq!((from as usize % SECTOR, to as usize % SECTOR), (0, 0));
+            if !self.powered() {
+                return Err(NorFlashErrorKind::Other);
+            }
+            self.bytes[from as usize..to as usize].fill(0xFF);
+            for sector in from as usize / SECTOR..to as usize / SECTOR {
+                self.erases[sector] += 1;
+            }
+            Ok(())
+        }
+
+        fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+            assert_eq!((offset as usize % 4, bytes.len() % 4), (0, 0));
+            for (i, &byte) in bytes.iter().enumerate() {
+                if !self.powered() {
+                    return Err(NorFlashErrorKind::Other);
+                }
+                let old = &mut self.bytes[offset as usize + i];
+                assert_eq!(*old & byte, byte, "writing over unerased flash");
+                *old = byte;
+            }
+            Ok(())
+        }
+    }
+
+    fn value(store: &mut Store<MockFlash>, key: Key) -> Option<Vec<u8>> {
+        let mut buf = [0; MAX_VALUE];
+        let len = store.read(key, &mut buf).unwrap()?;
+        Some(buf[..len].to_vec())
+    }
+
+    // Cut the power after `bytes` more, then boot again on what made it to flash
+    fn reboot_after(
+        mut store: Store<MockFlash>,
+        bytes: usize,
+        write: impl FnOnce(&mut Store<MockFlash>),
+    ) -> Store<MockFlash> {
+        store.flash.power = Some(bytes);
+        write(&mut store);
+        store.flash.power = None;
+        Store::mount(store.flash, SECTOR as u32, 3).unwrap()
+    }
+
+    #[test]
+    fn values_survive_a_remount() {
+        // The store starts one sector in, the first one isn't its own
+        let mut flash = MockFlash::new(4);
+        flash.bytes[..SECTOR].fill(0x42);
+        let mut store = Store::mount(flash, SECTOR as u32, 3).unwrap();
+        assert_eq!(value(&mut store, Key::Records), None);
+
+        store.write(Key::Records, b"first").unwrap();
+        store.write(Key::Calibration, &[7; 10]).unwrap();
+        store.write(Key::Records, b"second").unwrap();
+        assert_eq!(value(&mut store, Key::Records).unwrap(), b"second");
+
+        let mut store = Store::mount(store.flash, SECTOR as u32, 3).unwrap();
+        assert_eq!(value(&mut store, Key::Records).unwrap(), b"second");
+        assert_eq!(value(&mut store, Key::Calibration).unwrap(), [7; 10]);
+        assert_eq!(store.flash.bytes[..SECTOR], [0x42; SECTOR]);
+    }
+
+    #[test]
+    fn wear_spreads_over_every_sector() {
+        let mut store = Store::mount(MockFlash::new(4), SECTOR as u32, 3).unwrap();
+        store
+            .write(Key::Calibration, b"kept across compactions")
+            .unwrap();
+        for i in 0..600u32 {
+            store.write(Key::Records, &i.to_le_bytes()).unwrap();
+        }
+        let mut store = Store::mount(store.flash, SECTOR as u32, 3).unwrap();
+        assert_eq!(
+            value(&mut store, Key::Records).unwrap(),
+            599u32.to_le_bytes()
+        );
+        assert_eq!(
+            value(&mut store, Key::Calibration).unwrap(),
+            b"kept across compactions"
+        );
+        let erases = &store.flash.erases[1..];
+        assert!(erases.iter().all(|&n| n >= 5), "{erases:?}");
+        assert!(erases.iter().max().unwrap() - erases.iter().min().unwrap() <= 1);
+        assert_eq!(store.flash.erases[0], 0);
+    }
+
+    #[test]
+    fn torn_write_keeps_the_previous_value() {
+        let mut store = Store::mount(MockFlash::new(4), SECTOR as u32, 3).unwrap();
+        store.write(Key::Records, b"before the reset").unwrap();
+        // Half the header and value made it
+        let mut store = reboot_after(store, 10, |store| {
+            assert!(store.write(Key::Records, b"during the reset").is_err());
+        });
+        assert_eq!(
+            value(&mut store, Key::Records).unwrap(),
+            b"before the reset"
+        );
+
+        // The next write moves past the torn record
+        store.write(Key::Records, b"after the reset").unwrap();
+        store.write(Key::Calibration, b"and more").unwrap();
+        let mut store = Store::mount(store.flash, SECTOR as u32, 3).unwrap();
+        assert_eq!(value(&mut store, Key::Records).unwrap(), b"after the reset");
+        assert_eq!(value(&mut store, Key::Calibration).unwrap(), b"and more");
+    }
+
+    #[test]
+    fn cut_at_any_byte_keeps_old_or_new() {
+        let mut store = Store::mount(MockFlash::new(4), SECTOR as u32, 3).unwrap();
+        store.write(Key::Calibration, &[1; 40]).unwrap();
+        for _ in 0..4 {
+            store.write(Key::Records, &[2; 40]).unwrap();
+        }
+        // The next write compacts, the reset can land on any erase or byte of it
+        let flash = store.flash;
+        for budget in 0..400 {
+            let store = Store::mount(
+                MockFlash {
+                    bytes: flash.bytes.clone(),
+                    erases: flash.erases.clone(),
+                    power: None,
+                },
+                SECTOR as u32,
+                3,
+            )
+            .unwrap();
+            let mut store = reboot_after(store, budget, |store| {
+                let _ = store.write(Key::Records, &[3; 40]);
+            });
+            let records = value(&mut store, Key::Records).unwrap();
+            assert!(records == [2; 40] || records == [3; 40], "{budget}");
+            assert_eq!(value(&mut store, Key::Calibration).unwrap(), [1; 40]);
+        }
+    }
+
+    #[test]
+    fn values_must_fit() {
+        let mut store = Store::mount(MockFlash::new(2), 0, 2).unwrap();
+        assert_eq!(
+            store.write(Key::Records, &[0; MAX_VALUE + 1]),
+            Err(StoreError::TooLarge)
+        );
+        store.write(Key::Records, &[0; 16]).unwrap();
+        assert_eq!(
+            store.read(Key::Records, &mut [0; 8]),
+            Err(StoreError::TooLarge)
+        );
+    }
+}
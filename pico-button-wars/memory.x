@@ -1,6 +1,9 @@
 MEMORY {
     BOOT2 : ORIGIN = 0x10000000, LENGTH = 0x100
-    FLASH : ORIGIN = 0x10000100, LENGTH = 2048K - 0x100
+    FLASH : ORIGIN = 0x10000100, LENGTH = 2048K - 0x100 - 16K
+    /* Last 4 sectors kept out of the image for the wear leveled store: */
+    /* config, calibration and records, personal bests included         */
+    STORAGE : ORIGIN = 0x101FC000, LENGTH = 16K
 
     /* Pick one of the two options for RAM layout     */
 
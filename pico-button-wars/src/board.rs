@@ -0,0 +1,35 @@
+use embassy_rp::gpio::AnyPin;
+
+use button_wars_engine::{Config, ReactionDistribution, MAX_PLAYERS};
+
+// Players with a button, their pins come from the config in ButtonRole order
+pub const PLAYERS: usize = 2;
+
+pub const ONBOARD_LED_PIN: u8 = 25;
+
+// Config of a fresh board, a lone player gets a bot to play against. The saved one can set
+// or drop the bot too
+pub const DEFAULT_CONFIG: Config = Config {
+    bot: match PLAYERS {
+        1 => Some(ReactionDistribution::EASY),
+        _ => None,
+    },
+    ..Config::DEFAULT
+};
+
+// Checked at build time, the saved config is checked again when loaded
+const _: () = {
+    assert!(PLAYERS >= 1 && PLAYERS <= MAX_PLAYERS);
+    assert!(DEFAULT_CONFIG.is_valid(PLAYERS, &[ONBOARD_LED_PIN]));
+};
+
+// Everyone in a match with `config`, buttons then the bot
+pub fn seats(config: &Config) -> usize {
+    PLAYERS + config.bot.is_some() as usize
+}
+
+// GPIO of bank 0 by number, to build the buttons and LEDs from the config
+pub fn pin(number: u8) -> AnyPin {
+    // SAFETY: the config pins are checked distinct and never taken out of the peripherals elsewhere
+    unsafe { AnyPin::steal(number) }
+}
@@ -0,0 +1,22 @@
+use defmt::info;
+use embassy_time::{Instant, Timer};
+
+use button_wars_engine::{Bot, GameEvent};
+
+use crate::{events::EventSubscriber, input, rng::Rng};
+
+// Stands in for a button: answers every GO on the game bus with events on the button bus,
+// so the rounds can't tell it from a player
+#[embassy_executor::task]
+pub async fn play_bot(mut events: EventSubscriber, bot: Bot) {
+    info!("{} joins as a bot", bot);
+    loop {
+        let GameEvent::Go { at } = events.next_message_pure().await else {
+            continue;
+        };
+        for event in bot.on_go(at, &mut Rng) {
+            Timer::at(Instant::from_micros(event.at())).await;
+            input::publish(event);
+        }
+    }
+}
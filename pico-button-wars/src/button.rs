@@ -1,37 +1,34 @@
 use crate::common::LevelToStr;
+use crate::edge_capture::CapturedEdges;
+use crate::game::{FORFEIT, SOFT_RESET};
+use crate::input::ButtonSubscriber;
 
-use defmt::{debug, info, Format};
+pub use button_wars_engine::ButtonRole;
+use button_wars_engine::{
+    DebounceStrategy, DebouncedEdge, Debouncer, Gesture, GestureAction, GesturePattern,
+    GestureRecognizer, Micros, Recognized,
+};
+
+use defmt::{debug, info, warn, Format};
 use embassy_futures::select::{select, Either};
 use embassy_rp::gpio::{Input, Level, Pin, Pull};
-use embassy_rp::watchdog::*;
-use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
-use embassy_sync::mutex::Mutex;
-use embassy_time::{Duration, Instant, Ticker, Timer};
-
-// Could be subject to interrupt but OK for now
-pub type ButtonMutex = Mutex<ThreadModeRawMutex, Option<Button<'static>>>;
-
-// Debounce time with prior tests from measure_minimal_debounce()
-const MINIMAL_DEBOUNCE_TIME: u64 = 50;
-
-#[derive(PartialEq, Eq, Format, Clone, Copy, Debug, Hash)]
-pub enum ButtonRole {
-    Player1,
-    Player2,
-}
+use embassy_time::{Duration, Instant, Timer};
 
 pub struct Button<'a> {
     input: Input<'a>,
     role: ButtonRole,
-    debounce: Duration,
+    debouncer: DebounceStrategy,
+    // Hardware timestamped edges, replacing the GPIO interrupt once set
+    captured: Option<CapturedEdges>,
 }
 
 impl Button<'_> {
-    pub fn new<P: Pin>(pin: P, role: ButtonRole) -> Self {
+    pub fn new<P: Pin>(pin: P, role: ButtonRole, debouncer: DebounceStrategy) -> Self {
         Self {
             input: Input::new(pin, Pull::Up), // Initialize input with pull up
             role,
-            debounce: Duration::from_millis(MINIMAL_DEBOUNCE_TIME),
+            debouncer,
+            captured: None,
         }
     }
 
@@ -39,46 +36,102 @@ impl Button<'_> {
         self.role
     }
 
-    async fn wait_for_press(&mut self) -> Instant {
+    pub fn set_debouncer(&mut self, debouncer: DebounceStrategy) {
+        self.debouncer = debouncer;
+    }
+
+    // Take raw edges from the PIO capture instead of the GPIO interrupt
+    pub fn use_captured_edges(&mut self, captured: CapturedEdges) {
+        self.captured = Some(captured);
+    }
+
+    // Raw level, without debouncing
+    pub fn is_pressed(&self) -> bool {
+        self.input.is_low()
+    }
+
+    pub async fn wait_until_released(&mut self) {
+        self.input.wait_for_high().await;
+    }
+
+    // Longest a debounced edge can lag behind the raw one
+    pub fn debounce(&self) -> Duration {
+        Duration::from_micros(self.debouncer.latency())
+    }
+
+    // Returns the press edge instant, only once the debouncer confirmed it
+    pub async fn wait_for_press(&mut self) -> Instant {
         loop {
-            self.input.wait_for_falling_edge().await;
-            let press_instant = Instant::now();
-            Timer::after(self.debounce).await;
-            // safety in case debounce not enough
-            if self.input.get_level() == Level::Low {
+            let edge = self.wait_for_edge().await;
+            if edge.pressed {
                 info!("{} button pressed.", self.role);
-                return press_instant;
+                return Instant::from_micros(edge.at);
             }
         }
     }
 
-    async fn wait_for_release(&mut self) -> Instant {
+    // Returns the release edge instant, only once the debouncer confirmed it
+    pub async fn wait_for_release(&mut self) -> Instant {
         loop {
-            self.input.wait_for_low().await;
-            let release_instant = Instant::now();
-            Timer::after(self.debounce).await;
-            // safety in case debounce not enough
-            if self.input.get_level() == Level::High {
+            let edge = self.wait_for_edge().await;
+            if !edge.pressed {
                 info!("{} button released.", self.role);
-                return release_instant;
+                return Instant::from_micros(edge.at);
             }
         }
     }
 
-    pub async fn measure_full_press_release(&mut self) -> Instant {
-        self.wait_for_press().await;
-        return self.wait_for_release().await;
+    // Feed raw edges to the debouncer until it confirms one
+    async fn wait_for_edge(&mut self) -> DebouncedEdge {
+        loop {
+            let confirmed = match &self.captured {
+                Some(captured) => {
+                    let deadline = self.debouncer_deadline();
+                    match select(captured.receive(), Timer::at(deadline)).await {
+                        Either::First(edge) => self.debouncer.on_edge(edge.pressed, edge.at),
+                        Either::Second(_) => self.debouncer.poll(Instant::now().as_micros()),
+                    }
+                }
+                None => self.next_gpio_edge().await,
+            };
+            if let Some(edge) = confirmed {
+                return edge;
+            }
+        }
     }
 
-    pub async fn wait_for_full_press(&mut self) {
-        self.wait_for_press().await;
+    // Raw edge timestamped by software once the executor gets to it
+    async fn next_gpio_edge(&mut self) -> Option<DebouncedEdge> {
+        // An edge can slip by between two waits, catch up with the level first
+        let pressed = self.input.is_low();
+        if pressed != self.debouncer.raw_level() {
+            if let Some(edge) = self.debouncer.on_edge(pressed, Instant::now().as_micros()) {
+                return Some(edge);
+            }
+        }
+        let deadline = self.debouncer_deadline();
+        match select(self.input.wait_for_any_edge(), Timer::at(deadline)).await {
+            Either::First(_) => {
+                let now = Instant::now().as_micros();
+                self.debouncer.on_edge(self.input.is_low(), now)
+            }
+            Either::Second(_) => self.debouncer.poll(Instant::now().as_micros()),
+        }
+    }
+
+    fn debouncer_deadline(&self) -> Instant {
+        self.debouncer
+            .deadline()
+            .map(Instant::from_micros)
+            .unwrap_or(Instant::MAX)
     }
 
-    // Figure out minimal debounce time for button press
-    pub async fn _measure_minimal_debounce(
+    // Figure out minimal debounce time for button press, `on_press` gets the number of presses measured so far
+    pub async fn measure_minimal_debounce(
         &mut self,
         ms_test_range: u64,
         iterations: usize,
+        mut on_press: impl FnMut(usize),
     ) -> u64 {
         const MIN_DEBOUNCE_DEFAULT_IN_TEST: u64 = 50;
         info!(
@@ -147,6 +200,7 @@ impl Button<'_> {
             }
 
             total_transitions += transitions;
+            on_press(i + 1);
 
             info!(
                 "Found {} transitions with longest_debounce time of {} ms for test iteration i={}",
@@ -193,113 +247,59 @@ impl Format for Button<'_> {
     }
 }
 
-#[embassy_executor::task(pool_size = 1)]
-pub async fn monitor_double_longpress(
-    b1: &'static ButtonMutex,
-    b2: &'static ButtonMutex,
-    wd: &'static Mutex<ThreadModeRawMutex, Option<Watchdog>>,
-) {
-    // Less-blocking approach with 50 ms polling on button mutex
-    let mut ticker = Ticker::every(Duration::from_millis(50));
+// Holding your own button this long gives up the match, far past any press of a round. Held
+// with the other one it's the reset instead, however late that one came
+const FORFEIT_HOLD: Micros = 5_000_000;
+
+const fn forfeit(button: &'static [ButtonRole; 1]) -> Gesture {
+    Gesture {
+        pattern: GesturePattern::Chord {
+            buttons: button,
+            hold: FORFEIT_HOLD,
+        },
+        action: GestureAction::Forfeit,
+    }
+}
 
-    // Track long press state
-    let mut b1_pressed_time: Option<Instant> = None;
-    let mut b2_pressed_time: Option<Instant> = None;
-    let mut reset_countdown_active = false;
+// Gestures understood by the game. Only the first two players can forfeit, as only they can
+// reset
+pub const GESTURES: [Gesture; 3] = [
+    Gesture::RESET,
+    forfeit(&[ButtonRole::PLAYER_1]),
+    forfeit(&[ButtonRole::PLAYER_2]),
+];
 
+// Watch the button events for gestures and act on them, in every game state
+#[embassy_executor::task(pool_size = 1)]
+pub async fn recognize_gestures(mut buttons: ButtonSubscriber, gestures: &'static [Gesture]) {
+    let mut recognizer = GestureRecognizer::new(gestures);
     loop {
-        // Check both buttons without holding locks for too long
-        let b1_pressed = {
-            // Only try to lock for a short 10ms time before giving up
-            match select(b1.lock(), Timer::after(Duration::from_millis(10))).await {
-                Either::First(button_lock) => {
-                    if let Some(button) = button_lock.as_ref() {
-                        button.input.get_level() == Level::Low
-                    } else {
-                        false // Could not acquire lock
-                    }
-                }
-                Either::Second(_) => {
-                    // Couldn't get lock, maintain previous state
-                    b1_pressed_time.is_some()
-                }
-            }
+        let deadline = recognizer
+            .deadline()
+            .map(Instant::from_micros)
+            .unwrap_or(Instant::MAX);
+        let recognized = match select(buttons.next_message_pure(), Timer::at(deadline)).await {
+            Either::First(event) => recognizer.on_event(&event),
+            Either::Second(_) => recognizer.poll(Instant::now().as_micros()),
         };
-
-        let b2_pressed = {
-            // Only try to lock for a short time before giving up
-            match select(b2.lock(), Timer::after(Duration::from_millis(10))).await {
-                Either::First(button_lock) => {
-                    if let Some(button) = button_lock.as_ref() {
-                        button.input.get_level() == Level::Low
-                    } else {
-                        false // Could not acquire lock
-                    }
-                }
-                Either::Second(_) => {
-                    // Couldn't get lock, maintain previous state
-                    b2_pressed_time.is_some()
-                }
+        match recognized {
+            Some(Recognized {
+                action: GestureAction::Reset,
+                ..
+            }) => {
+                info!("Reset gesture recognized. Soft reset to waiting...");
+                SOFT_RESET.signal(());
             }
-        };
-
-        // Update press times
-        if b1_pressed && b1_pressed_time.is_none() {
-            b1_pressed_time = Some(Instant::now());
-            debug!("Button 1 pressed");
-        }
-
-        if b2_pressed && b2_pressed_time.is_none() {
-            b2_pressed_time = Some(Instant::now());
-            debug!("Button 2 pressed");
-        }
-
-        // Check for button releases
-        if !b1_pressed && b1_pressed_time.is_some() {
-            let duration = b1_pressed_time.unwrap().elapsed();
-            debug!("Button 1 released after {} ms", duration.as_millis());
-            b1_pressed_time = None;
-            reset_countdown_active = false;
-        }
-
-        if !b2_pressed && b2_pressed_time.is_some() {
-            let duration = b2_pressed_time.unwrap().elapsed();
-            debug!("Button 2 released after {} ms", duration.as_millis());
-            b2_pressed_time = None;
-            reset_countdown_active = false;
-        }
-
-        // Check for longpress condition
-        if let (Some(t1), Some(t2)) = (b1_pressed_time, b2_pressed_time) {
-            let b1_duration = t1.elapsed();
-            let b2_duration = t2.elapsed();
-
-            if !reset_countdown_active
-                && b1_duration.as_millis() >= 1000
-                && b2_duration.as_millis() >= 1000
-            {
-                reset_countdown_active = true;
-                info!(
-                    "Both buttons held for 1+ second. Continuing to monitor for reset threshold..."
-                );
-            }
-
-            // Check if press duration is enough to trigger reset
-            if b1_duration.as_millis() >= 3000 && b2_duration.as_millis() >= 3000 {
-                info!(
-                    "Long press detected on both buttons (b1={} ms, b2={} ms). Resetting via watchdog...",
-                    b1_duration.as_millis(),
-                    b2_duration.as_millis()
-                );
-
-                // Lock the watchdog to prevent feeding
-                let _lock_forever = wd.lock().await;
-                loop {
-                    Timer::after_secs(10).await; // Keep the lock forever
-                }
+            Some(Recognized {
+                action: GestureAction::Forfeit,
+                player: Some(player),
+                ..
+            }) => {
+                info!("{} gives up", player);
+                FORFEIT.signal(player);
             }
+            Some(other) => warn!("Gesture {} without a player, ignored", other),
+            None => {}
         }
-
-        ticker.next().await;
     }
 }
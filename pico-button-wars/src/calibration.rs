@@ -0,0 +1,73 @@
+use defmt::info;
+use embassy_time::{Duration, Timer};
+
+use button_wars_engine::{DebounceCalibration, DebounceStrategy, Key, MAX_PLAYERS};
+
+use crate::button::{Button, ButtonRole};
+use crate::config::config;
+use crate::led::{all_leds_off, player_led, Led, LedRole};
+use crate::storage::{load, save, BoardStore};
+
+// Presses measured per button
+const CALIBRATION_PRESSES: usize = 10;
+// Window watched for bounces after each press, in ms
+const CALIBRATION_WINDOW_MS: u64 = 100;
+
+// Calibration saved by a previous boot, if any
+pub fn load_calibration(store: &mut BoardStore) -> Option<DebounceCalibration> {
+    load(store, Key::Calibration, DebounceCalibration::from_bytes)
+}
+
+pub fn store_calibration(store: &mut BoardStore, calibration: &DebounceCalibration) {
+    info!("Saving {}", calibration);
+    save(store, Key::Calibration, &calibration.to_bytes());
+}
+
+// Debouncer the config picks for a button, over the time the calibration measured on it, else
+// the config's one
+pub fn debouncer(calibration: Option<DebounceCalibration>, role: ButtonRole) -> DebounceStrategy {
+    let config = config();
+    let debounce = calibration
+        .and_then(|calibration| calibration.debounce(role))
+        .unwrap_or(config.debounce_ms as u64 * 1_000);
+    config.debouncers[role.index()].strategy(debounce)
+}
+
+// The first two buttons held while booting enters the calibration, like the reset chord
+pub fn boot_combo_held(buttons: &[Button<'_>]) -> bool {
+    buttons.len() >= 2 && buttons[..2].iter().all(|button| button.is_pressed())
+}
+
+// Measure each button in turn, its LED stays on while it's measured and the onboard LED blinks once per press
+pub async fn calibrate(buttons: &mut [Button<'_>], leds: &mut [Led<'_>]) -> DebounceCalibration {
+    info!("Calibration mode, release every button...");
+    for button in buttons.iter_mut() {
+        button.wait_until_released().await;
+    }
+
+    let mut debounce_ms = [0; MAX_PLAYERS];
+    for button in buttons {
+        let role = button.role();
+        if let Some(led) = player_led(leds, role) {
+            led.turn_on();
+        }
+        let mut onboard = leds.iter_mut().find(|led| led.role() == LedRole::Onboard);
+        let ms = button
+            .measure_minimal_debounce(CALIBRATION_WINDOW_MS, CALIBRATION_PRESSES, |press| {
+                if let Some(onboard) = onboard.as_mut() {
+                    match press % 2 {
+                        1 => onboard.turn_on(),
+                        _ => onboard.turn_off(),
+                    }
+                }
+            })
+            .await;
+        debounce_ms[role.index()] = ms.min(u16::MAX as u64) as u16;
+        all_leds_off(leds);
+        if let Some(led) = player_led(leds, role) {
+            led.flash_pattern(Duration::from_millis(100), 3).await;
+        }
+        Timer::after_millis(500).await;
+    }
+    DebounceCalibration { debounce_ms }
+}
@@ -1,5 +1,4 @@
 use embassy_rp::gpio::Level;
-use embassy_time::Instant;
 
 pub trait LevelToStr {
     fn level_to_str(&self, level: &Level) -> &str {
@@ -9,34 +8,3 @@ pub trait LevelToStr {
         }
     }
 }
-
-// Silly number generator from 0 to 10000 as u64
-
-pub struct SimpleRngU64 {
-    seed: u64,
-}
-
-impl SimpleRngU64 {
-    pub fn new() -> Self {
-        // Use the current time as initial seed
-        let now = Instant::now();
-        let seed = now.as_micros();
-        Self { seed }
-    }
-
-    // Seed update
-    pub fn next_u64(&mut self) -> u64 {
-        const A: u64 = 1664525;
-        const C: u64 = 1013904223;
-        self.seed = self.seed.wrapping_mul(A).wrapping_add(C);
-        self.seed
-    }
-
-    // Linear congruential generator implementation
-    pub fn generate_from_range(&mut self, from: u64, to: u64) -> u64 {
-        if from >= to {
-            return from;
-        }
-        from + (self.next_u64() % (to - from + 1))
-    }
-}
@@ -0,0 +1,53 @@
+use core::cell::Cell;
+
+use defmt::{info, warn};
+use embassy_sync::blocking_mutex::{raw::CriticalSectionRawMutex, Mutex};
+
+use button_wars_engine::{Config, Key};
+
+use crate::board::{DEFAULT_CONFIG, ONBOARD_LED_PIN, PLAYERS};
+use crate::storage::{load, save, BoardStore};
+
+// Settings in use, replaced by the saved ones at boot
+static CONFIG: Mutex<CriticalSectionRawMutex, Cell<Config>> = Mutex::new(Cell::new(DEFAULT_CONFIG));
+
+pub fn config() -> Config {
+    CONFIG.lock(Cell::get)
+}
+
+// Saved config, upgraded and saved again if an older firmware wrote it. The default when
+// there's none or it doesn't suit this board, e.g. pins taken twice or a watchdog it can't arm
+pub fn load_config(store: &mut BoardStore) -> Config {
+    let config = match load(store, Key::Config, Config::from_bytes) {
+        Some((config, version)) if !config.is_valid(PLAYERS, &[ONBOARD_LED_PIN]) => {
+            warn!(
+                "Saved config v{} doesn't suit this board, using the default",
+                version
+            );
+            DEFAULT_CONFIG
+        }
+        Some((config, version)) => {
+            if version < Config::VERSION {
+                info!(
+                    "Migrating the config from v{} to v{}",
+                    version,
+                    Config::VERSION
+                );
+                store_config(store, &config);
+            }
+            config
+        }
+        None => DEFAULT_CONFIG,
+    };
+    set_config(config);
+    config
+}
+
+// Settings read at boot only take effect after the next one
+pub fn set_config(config: Config) {
+    CONFIG.lock(|current| current.set(config));
+}
+
+pub fn store_config(store: &mut BoardStore, config: &Config) -> bool {
+    save(store, Key::Config, &config.to_bytes())
+}
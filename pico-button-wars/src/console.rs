@@ -0,0 +1,342 @@
+use core::cell::{Cell, RefCell};
+use core::fmt::Write;
+
+use defmt::{info, unwrap, warn};
+use embassy_executor::Spawner;
+use embassy_rp::bind_interrupts;
+use embassy_rp::peripherals::USB;
+use embassy_rp::usb::{Driver, InterruptHandler};
+use embassy_sync::blocking_mutex::{raw::CriticalSectionRawMutex, Mutex};
+use embassy_sync::{channel::Channel, pubsub::WaitResult};
+use embassy_usb::class::cdc_acm::{CdcAcmClass, State};
+use embassy_usb::driver::EndpointError;
+use embassy_usb::{Builder, UsbDevice};
+use heapless::String;
+use static_cell::StaticCell;
+
+use button_wars_engine::{
+    as_millis, console::HELP, ButtonRole, Command, GameState, GameStatus, LineBuffer, MatchFormat,
+    MatchStats, Records, Setting,
+};
+
+use crate::board::{ONBOARD_LED_PIN, PLAYERS};
+use crate::config::{config, set_config, store_config};
+use crate::events::EventSubscriber;
+use crate::game::SOFT_RESET;
+use crate::storage::STORE;
+
+bind_interrupts!(struct Irqs {
+    USBCTRL_IRQ => InterruptHandler<USB>;
+});
+
+type UsbDriver = Driver<'static, USB>;
+
+// Full speed bulk endpoints
+const PACKET_SIZE: usize = 64;
+// Longest reply, the whole config or the records
+const REPLY_SIZE: usize = 1024;
+
+// Only the waiting menu acts on these, the main loop picks them up there
+pub enum MenuCommand {
+    Start,
+    Format(MatchFormat),
+}
+
+pub static MENU_COMMANDS: Channel<CriticalSectionRawMutex, MenuCommand, 2> = Channel::new();
+
+// Followed from the event bus for status
+static STATUS: Mutex<CriticalSectionRawMutex, Cell<GameStatus>> =
+    Mutex::new(Cell::new(GameStatus::new(MatchFormat::PRESETS[0])));
+
+// Stats of the last match, kept from its results until the next one
+static LAST_STATS: Mutex<CriticalSectionRawMutex, RefCell<Option<MatchStats>>> =
+    Mutex::new(RefCell::new(None));
+
+pub fn set_last_stats(stats: &MatchStats) {
+    LAST_STATS.lock(|last| *last.borrow_mut() = Some(stats.clone()));
+}
+
+// Copy of the records main keeps, so a console read never touches the flash
+static RECORDS: Mutex<CriticalSectionRawMutex, Cell<Option<Records>>> = Mutex::new(Cell::new(None));
+
+pub fn set_records(records: &Records) {
+    RECORDS.lock(|shown| shown.set(Some(*records)));
+}
+
+// Each line of a reply ends in CR LF for the terminals, a full reply is cut short
+macro_rules! reply {
+    ($reply:expr, $($arg:tt)*) => {{
+        let _ = write!($reply, $($arg)*);
+        let _ = $reply.push_str("\r\n");
+    }};
+}
+
+// CDC-ACM serial port on the USB connector, any terminal at any baud rate talks to the game
+// `seats` of the matches this boot plays, the bot's included
+pub fn start_console(spawner: &Spawner, usb: USB, events: EventSubscriber, seats: usize) {
+    let mut usb_config = embassy_usb::Config::new(0xc0de, 0xcafe);
+    usb_config.manufacturer = Some("Pico Button Wars");
+    usb_config.product = Some("Button Wars console");
+    usb_config.max_power = 100;
+    usb_config.max_packet_size_0 = PACKET_SIZE as u8;
+    // Interface association descriptors, so Windows binds its CDC driver too
+    usb_config.device_class = 0xEF;
+    usb_config.device_sub_class = 0x02;
+    usb_config.device_protocol = 0x01;
+    usb_config.composite_with_iads = true;
+
+    static CONFIG_DESCRIPTOR: StaticCell<[u8; 256]> = StaticCell::new();
+    static BOS_DESCRIPTOR: StaticCell<[u8; 256]> = StaticCell::new();
+    static CONTROL_BUF: StaticCell<[u8; 64]> = StaticCell::new();
+    static CDC_STATE: StaticCell<State> = StaticCell::new();
+    let mut builder = Builder::new(
+        Driver::new(usb, Irqs),
+        usb_config,
+        CONFIG_DESCRIPTOR.init([0; 256]),
+        BOS_DESCRIPTOR.init([0; 256]),
+        &mut [],
+        CONTROL_BUF.init([0; 64]),
+    );
+    let class = CdcAcmClass::new(
+        &mut builder,
+        CDC_STATE.init(State::new()),
+        PACKET_SIZE as u16,
+    );
+    let device = builder.build();
+
+    unwrap!(spawner.spawn(run_usb(device)));
+    unwrap!(spawner.spawn(track_status(events)));
+    unwrap!(spawner.spawn(serve_console(class, seats)));
+}
+
+#[embassy_executor::task]
+async fn run_usb(mut device: UsbDevice<'static, UsbDriver>) -> ! {
+    device.run().await
+}
+
+#[embassy_executor::task]
+async fn track_status(mut events: EventSubscriber) {
+    STATUS.lock(|status| status.set(GameStatus::new(config().format)));
+    loop {
+        match events.next_message().await {
+            WaitResult::Message(event) => STATUS.lock(|status| {
+                let mut updated = status.get();
+                updated.on_event(&event);
+                status.set(updated);
+            }),
+            WaitResult::Lagged(missed) => {
+                warn!("Console status lagged behind, {} events missed", missed)
+            }
+        }
+    }
+}
+
+#[embassy_executor::task]
+async fn serve_console(mut class: CdcAcmClass<'static, UsbDriver>, seats: usize) {
+    loop {
+        class.wait_connection().await;
+        info!("Console connected");
+        // Only fails once the host is gone
+        let _ = serve(&mut class, seats).await;
+        info!("Console disconnected");
+    }
+}
+
+async fn serve(
+    class: &mut CdcAcmClass<'static, UsbDriver>,
+    seats: usize,
+) -> Result<(), EndpointError> {
+    let mut line = LineBuffer::new();
+    let mut packet = [0; PACKET_SIZE];
+    let mut reply: String<REPLY_SIZE> = String::new();
+    write(class, "Button Wars console, type help\r\n> ").await?;
+    loop {
+        let len = class.read_packet(&mut packet).await?;
+        for &byte in &packet[..len] {
+            reply.clear();
+            echo(&mut reply, byte);
+            if let Some(typed) = line.push(byte) {
+                match typed.and_then(Command::parse) {
+                    Ok(Some(command)) => execute(command, seats, &mut reply).await,
+                    Ok(None) => {}
+                    Err(e) => reply!(reply, "error: {}", e),
+                }
+                let _ = reply.push_str("> ");
+            }
+            write(class, &reply).await?;
+        }
+    }
+}
+
+// What the terminal shows of a typed byte
+fn echo(reply: &mut String<REPLY_SIZE>, byte: u8) {
+    let _ = match byte {
+        b'\r' => reply.push_str("\r\n"),
+        0x08 | 0x7F => reply.push_str("\x08 \x08"),
+        b' '..=b'~' => reply.push(byte as char),
+        _ => Ok(()),
+    };
+}
+
+async fn write(
+    class: &mut CdcAcmClass<'static, UsbDriver>,
+    text: &str,
+) -> Result<(), EndpointError> {
+    for chunk in text.as_bytes().chunks(PACKET_SIZE) {
+        class.write_packet(chunk).await?;
+    }
+    // A full last packet leaves the host waiting for more without an empty one
+    if !text.is_empty() && text.len() % PACKET_SIZE == 0 {
+        class.write_packet(&[]).await?;
+    }
+    Ok(())
+}
+
+async fn execute(command: Command, seats: usize, reply: &mut String<REPLY_SIZE>) {
+    info!("Console command {}", command);
+    let status = STATUS.lock(Cell::get);
+    match command {
+        Command::Help => {
+            for line in HELP.lines() {
+                reply!(reply, "{}", line);
+            }
+        }
+        Command::Status => {
+            reply!(reply, "{:?}, format {}", status.state, status.format);
+            if let Some(round) = status.round {
+                reply!(reply, "round #{}", round);
+            }
+            for player in ButtonRole::first(seats) {
+                reply!(reply, "{}: {}", player, status.scores[player.index()]);
+            }
+            if let Some(winner) = status.last_winner {
+                reply!(reply, "last match won by {}", winner);
+            }
+        }
+        Command::Start => {
+            if to_menu(&status, MenuCommand::Start, reply) {
+                reply!(reply, "starting a {} match", status.format);
+            }
+        }
+        Command::Reset => {
+            SOFT_RESET.signal(());
+            reply!(reply, "back to waiting");
+        }
+        Command::Stats => match LAST_STATS.lock(|last| last.borrow().clone()) {
+            None => reply!(reply, "no match played since boot"),
+            Some(stats) => {
+                reply!(reply, "{} rounds played", stats.rounds_played);
+                for player in &stats.players {
+                    reply!(
+                        reply,
+                        "{}: {} wins, {} false starts, {} anticipations, {} missed GO",
+                        player.player,
+                        player.wins,
+                        player.false_starts,
+                        player.anticipations,
+                        player.no_presses
+                    );
+                    if let Some(reaction) = player.reaction {
+                        reply!(
+                            reply,
+                            "  reaction mean {} ms, best {} ms, consistency {}/1000",
+                            as_millis(reaction.mean),
+                            as_millis(reaction.min),
+                            reaction.consistency
+                        );
+                    }
+                }
+            }
+        },
+        Command::Records => {
+            let Some(records) = RECORDS.lock(Cell::get) else {
+                reply!(reply, "records not loaded yet");
+                return;
+            };
+            match records.best_reaction {
+                Some((player, time)) => {
+                    reply!(
+                        reply,
+                        "best reaction ever {} ms by {}",
+                        as_millis(time),
+                        player
+                    )
+                }
+                None => reply!(reply, "no record yet"),
+            }
+            for (format, best) in MatchFormat::PRESETS.iter().zip(records.mode_best) {
+                if let Some(best) = best {
+                    reply!(reply, "best in {}: {} ms", format, as_millis(best));
+                }
+            }
+            if let Some(best) = records.practice_best() {
+                reply!(reply, "best in practice: {} ms", as_millis(best));
+            }
+            reply!(reply, "{} matches played", records.matches_played);
+            // The bot seat holds no records
+            for player in ButtonRole::first(PLAYERS) {
+                reply!(reply, "{}: {} wins", player, records.wins[player.index()]);
+            }
+        }
+        Command::Mode(None) => reply!(reply, "format {}", status.format),
+        Command::Mode(Some(format)) => {
+            if to_menu(&status, MenuCommand::Format(format), reply) {
+                reply!(reply, "format set to {}", format);
+            }
+        }
+        Command::ConfigGet(Some(setting)) => {
+            reply!(reply, "{} = {}", setting, config().get(setting))
+        }
+        Command::ConfigGet(None) => {
+            let config = config();
+            for setting in Setting::all(PLAYERS) {
+                reply!(reply, "{} = {}", setting, config.get(setting));
+            }
+        }
+        Command::ConfigSet(setting, value) => {
+            let Some(updated) = config().with_setting(setting, value, PLAYERS, &[ONBOARD_LED_PIN])
+            else {
+                reply!(
+                    reply,
+                    "refused, {} can't be {} on this board",
+                    setting,
+                    value
+                );
+                return;
+            };
+            set_config(updated);
+            let saved = match STORE.lock().await.as_mut() {
+                Some(store) => store_config(store, &updated),
+                None => false,
+            };
+            match (saved, setting.applies_at_boot()) {
+                (false, _) => reply!(
+                    reply,
+                    "{} = {} until reboot, could not save it",
+                    setting,
+                    value
+                ),
+                (true, true) => reply!(
+                    reply,
+                    "{} = {}, saved, applies after a reboot",
+                    setting,
+                    value
+                ),
+                (true, false) => reply!(reply, "{} = {}, saved", setting, value),
+            }
+        }
+    }
+}
+
+// Hand a command over to the waiting menu, false with the reason in the reply when it can't take it
+fn to_menu(status: &GameStatus, command: MenuCommand, reply: &mut String<REPLY_SIZE>) -> bool {
+    if status.state != GameState::Waiting {
+        reply!(reply, "only from the waiting menu, reset first");
+        return false;
+    }
+    if MENU_COMMANDS.try_send(command).is_err() {
+        reply!(reply, "menu busy, try again");
+        return false;
+    }
+    true
+}
@@ -0,0 +1,166 @@
+use defmt::{info, warn};
+use embassy_futures::select::{select4, Either4};
+use embassy_rp::bind_interrupts;
+use embassy_rp::clocks::clk_sys_freq;
+use embassy_rp::peripherals::PIO0;
+use embassy_rp::pio::{
+    Common, Config, FifoJoin, InterruptHandler, Pio, ShiftConfig, ShiftDirection, StateMachine,
+};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::channel::{Channel, Receiver};
+use embassy_time::Instant;
+use fixed::types::U24F8;
+
+use button_wars_engine::{CapturedEdge, EdgeCaptureDecoder};
+
+use crate::button::ButtonRole;
+
+bind_interrupts!(struct Irqs {
+    PIO0_IRQ_0 => InterruptHandler<PIO0>;
+});
+
+// Program clock, 5 cycles per loop makes the counter tick every microsecond
+const PROGRAM_HZ: u32 = 5_000_000;
+
+// Raw edges waiting for their button task, a bounce burst fits
+const CAPTURED_EDGE_CAPACITY: usize = 8;
+
+pub type CapturedEdges =
+    Receiver<'static, CriticalSectionRawMutex, CapturedEdge, CAPTURED_EDGE_CAPACITY>;
+
+// One state machine per button, PIO0 has four
+pub const CAPTURE_SLOTS: usize = 4;
+
+// Raw edges of each button, by role index
+static CAPTURED_EDGES: [Channel<CriticalSectionRawMutex, CapturedEdge, CAPTURED_EDGE_CAPACITY>;
+    CAPTURE_SLOTS] = [const { Channel::new() }; CAPTURE_SLOTS];
+
+type CaptureMachines = (
+    StateMachine<'static, PIO0, 0>,
+    StateMachine<'static, PIO0, 1>,
+    StateMachine<'static, PIO0, 2>,
+    StateMachine<'static, PIO0, 3>,
+);
+
+// Start one state machine per button pin, all in the same cycle so their counters agree.
+// PIO reads GPIO inputs whatever their function, so the pins stay regular inputs for the buttons.
+// Machines without a pin stay disabled and never push
+pub fn start_edge_capture(pio: PIO0, pins: &[u8]) -> (CaptureMachines, EdgeCaptureDecoder) {
+    assert!(pins.len() <= CAPTURE_SLOTS);
+    let Pio {
+        mut common,
+        sm0,
+        sm1,
+        sm2,
+        sm3,
+        ..
+    } = Pio::new(pio, Irqs);
+
+    // Loops of 5 cycles, each one decrementing x: the level of an edge then x go out in one word.
+    // The push doesn't block so a full FIFO drops edges instead of stopping the count
+    let program = pio_proc::pio_asm!(
+        "    set y, 1",
+        ".wrap_target",
+        "released:",
+        "    jmp x-- released_pin",
+        "released_pin:",
+        "    jmp pin released [3]",
+        "    in y, 1",
+        "    in x, 31",
+        "    push noblock",
+        "    jmp x-- pressed [1]",
+        "pressed:",
+        "    jmp x-- pressed_pin",
+        "pressed_pin:",
+        "    jmp pin release [2]",
+        "    jmp pressed",
+        "release:",
+        "    in null, 1",
+        "    in x, 31",
+        "    push noblock",
+        "    jmp x-- released [2]",
+        ".wrap",
+    );
+    let program = common.load_program(&program.program);
+
+    let mut config = Config::default();
+    config.use_program(&program, &[]);
+    // Computed in 1/256ths, the system clock itself doesn't fit the divider type
+    config.clock_divider =
+        U24F8::from_bits((clk_sys_freq() as u64 * 256 / PROGRAM_HZ as u64) as u32);
+    config.shift_in = ShiftConfig {
+        threshold: 32,
+        direction: ShiftDirection::Left,
+        auto_fill: false,
+    };
+    config.fifo_join = FifoJoin::RxOnly;
+    let mut machines = (sm0, sm1, sm2, sm3);
+    configure(&mut machines.0, &mut config, pins.first());
+    configure(&mut machines.1, &mut config, pins.get(1));
+    configure(&mut machines.2, &mut config, pins.get(2));
+    configure(&mut machines.3, &mut config, pins.get(3));
+
+    start(&mut common, &mut machines, pins.len());
+    let decoder = EdgeCaptureDecoder::new(Instant::now().as_micros());
+    info!("Edge capture started on pins {}", pins);
+    (machines, decoder)
+}
+
+fn configure<const SM: usize>(
+    sm: &mut StateMachine<'static, PIO0, SM>,
+    config: &mut Config<'static, PIO0>,
+    pin: Option<&u8>,
+) {
+    let Some(&pin) = pin else {
+        return;
+    };
+    let mut exec = config.get_exec();
+    exec.jmp_pin = pin;
+    // SAFETY: only the jump pin changes, it's read and never driven
+    unsafe { config.set_exec(exec) };
+    sm.set_config(config);
+}
+
+fn start(common: &mut Common<'static, PIO0>, machines: &mut CaptureMachines, used: usize) {
+    let (sm0, sm1, sm2, sm3) = machines;
+    common.apply_sm_batch(|batch| {
+        batch.restart(sm0);
+        batch.restart(sm1);
+        batch.restart(sm2);
+        batch.restart(sm3);
+        batch.set_enable(sm0, used > 0);
+        batch.set_enable(sm1, used > 1);
+        batch.set_enable(sm2, used > 2);
+        batch.set_enable(sm3, used > 3);
+    });
+}
+
+pub fn captured_edges(role: ButtonRole) -> CapturedEdges {
+    CAPTURED_EDGES[role.index()].receiver()
+}
+
+// Decode the words of the state machines and hand the edges to their button
+#[embassy_executor::task]
+pub async fn forward_captured_edges(machines: CaptureMachines, decoder: EdgeCaptureDecoder) {
+    let (mut sm0, mut sm1, mut sm2, mut sm3) = machines;
+    loop {
+        let (index, word) = match select4(
+            sm0.rx().wait_pull(),
+            sm1.rx().wait_pull(),
+            sm2.rx().wait_pull(),
+            sm3.rx().wait_pull(),
+        )
+        .await
+        {
+            Either4::First(word) => (0, word),
+            Either4::Second(word) => (1, word),
+            Either4::Third(word) => (2, word),
+            Either4::Fourth(word) => (3, word),
+        };
+        let role = ButtonRole::ALL[index];
+        let edge = decoder.decode(word, Instant::now().as_micros());
+        if CAPTURED_EDGES[role.index()].try_send(edge).is_err() {
+            warn!("{} edge dropped, its button task is lagging", role);
+        }
+    }
+}
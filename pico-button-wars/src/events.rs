@@ -0,0 +1,141 @@
+use defmt::{info, warn};
+use embassy_sync::{
+    blocking_mutex::raw::CriticalSectionRawMutex,
+    pubsub::{PubSubChannel, Subscriber, WaitResult},
+};
+
+use button_wars_engine::{as_millis, ButtonRole, GameEvent, ScoringMetric, Trial, Verdict};
+
+// Events kept for the slowest subscriber before it starts lagging
+const EVENT_CAPACITY: usize = 16;
+// Room for logging, LEDs, a display and telemetry
+const EVENT_SUBSCRIBERS: usize = 4;
+// Everything is published with the immediate publisher, which takes no slot
+const EVENT_PUBLISHERS: usize = 1;
+
+pub type EventSubscriber = Subscriber<
+    'static,
+    CriticalSectionRawMutex,
+    GameEvent,
+    EVENT_CAPACITY,
+    EVENT_SUBSCRIBERS,
+    EVENT_PUBLISHERS,
+>;
+
+// Game event bus, any task can subscribe to the stream
+pub static EVENTS: PubSubChannel<
+    CriticalSectionRawMutex,
+    GameEvent,
+    EVENT_CAPACITY,
+    EVENT_SUBSCRIBERS,
+    EVENT_PUBLISHERS,
+> = PubSubChannel::new();
+
+// Never waits on subscribers: a full queue drops the oldest event and laggers get told so
+pub fn publish(event: GameEvent) {
+    EVENTS.immediate_publisher().publish_immediate(event);
+}
+
+// Log every event on the bus, so the game loop doesn't have to
+#[embassy_executor::task]
+pub async fn log_events(mut events: EventSubscriber, scoring: ScoringMetric) {
+    loop {
+        let event = match events.next_message().await {
+            WaitResult::Message(event) => event,
+            WaitResult::Lagged(missed) => {
+                warn!("Event logger lagged behind, {} events missed", missed);
+                continue;
+            }
+        };
+        match event {
+            GameEvent::StateChanged { from, to, duration } => info!(
+                "Transition {}->{} after {} ms",
+                from,
+                to,
+                as_millis(duration)
+            ),
+            GameEvent::RoundStarted { index } => info!("Round #{} started", index),
+            GameEvent::Go { .. } => info!("GO!"),
+            GameEvent::ButtonPressed { player, at } => {
+                info!("{} pressed at {} ms from boot", player, as_millis(at))
+            }
+            GameEvent::RoundWon(outcome) => {
+                let result = outcome.result;
+                match (result.verdict, result.winner, result.response_time) {
+                    (Verdict::Fastest, Some(winner), Some(time)) => info!(
+                        "DINGINGINGING! Congratulations for {} with a {} time of {} ms (reaction {} ms)",
+                        winner,
+                        scoring,
+                        as_millis(time),
+                        result.reaction_time.map(as_millis)
+                    ),
+                    (Verdict::FalseStart, Some(winner), _) => {
+                        info!("{} takes the round on a false start!", winner)
+                    }
+                    (Verdict::Tie(policy), Some(winner), _) => {
+                        info!("Tie settled by {}, {} takes the round!", policy, winner)
+                    }
+                    (Verdict::Tie(policy), None, _) => {
+                        info!("Tie within the threshold, {}! Nobody scores.", policy)
+                    }
+                    (Verdict::NoReaction, _, _) => {
+                        info!("Nobody pressed in time, nobody scores this round.")
+                    }
+                    _ => info!("Everybody false started, nobody scores this round."),
+                }
+                for (player, result) in ButtonRole::ALL.iter().zip(result.players.iter()) {
+                    info!("{}: {}", player, result);
+                }
+                if let Some(margin) = result.margin {
+                    info!("Won by a margin of {} ms", as_millis(margin));
+                }
+            }
+            GameEvent::MatchWon { winner } => info!("{} wins the match!", winner),
+            GameEvent::FormatChanged { format } => info!("Match format set to {}", format),
+            GameEvent::TrialDone {
+                player,
+                index,
+                trial,
+                personal_best,
+            } => match trial {
+                Trial::Reacted(time) => info!(
+                    "{} trial #{}: {} ms (personal best {} ms)",
+                    player,
+                    index,
+                    as_millis(time),
+                    personal_best.map(as_millis)
+                ),
+                Trial::FalseStart => warn!("{} trial #{}: false start!", player, index),
+                Trial::Anticipated(time) => warn!(
+                    "{} trial #{}: {} ms is too fast to be a reaction, anticipation!",
+                    player,
+                    index,
+                    as_millis(time)
+                ),
+                Trial::NoPress => info!("{} trial #{}: no press in time", player, index),
+            },
+            GameEvent::PracticeDone { summary, new_best } => {
+                info!(
+                    "{} practice over: {} trials, {} false starts, {} anticipations, {} missed GO",
+                    summary.player,
+                    summary.trials,
+                    summary.false_starts,
+                    summary.anticipations,
+                    summary.no_presses
+                );
+                if let Some(reaction) = summary.reaction {
+                    info!(
+                        "{} mean {} ms, best {} ms, consistency {}/1000",
+                        summary.player,
+                        as_millis(reaction.mean),
+                        as_millis(reaction.min),
+                        reaction.consistency
+                    );
+                }
+                if new_best {
+                    info!("New personal best for {}!", summary.player);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use core::pin::pin;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use defmt::Format;
+use embassy_futures::select::{select, Either};
+use embassy_sync::{
+    blocking_mutex::raw::CriticalSectionRawMutex,
+    pubsub::{PubSubChannel, Subscriber},
+};
+use embassy_time::{Duration, Instant, Timer};
+
+use button_wars_engine::{ButtonDecoder, ButtonEvent, ClickConfig, MAX_PLAYERS};
+
+use crate::button::{Button, ButtonRole};
+
+// Events kept for the slowest subscriber before it starts lagging
+const BUTTON_EVENT_CAPACITY: usize = 16;
+// The game loop and the gesture recognizer, plus room for one more
+const BUTTON_EVENT_SUBSCRIBERS: usize = 3;
+// Button tasks publish with the immediate publisher, which takes no slot
+const BUTTON_EVENT_PUBLISHERS: usize = 1;
+
+pub type ButtonSubscriber = Subscriber<
+    'static,
+    CriticalSectionRawMutex,
+    ButtonEvent,
+    BUTTON_EVENT_CAPACITY,
+    BUTTON_EVENT_SUBSCRIBERS,
+    BUTTON_EVENT_PUBLISHERS,
+>;
+
+// Events of every button, nobody has to own or lock a button to follow it
+pub static BUTTON_EVENTS: PubSubChannel<
+    CriticalSectionRawMutex,
+    ButtonEvent,
+    BUTTON_EVENT_CAPACITY,
+    BUTTON_EVENT_SUBSCRIBERS,
+    BUTTON_EVENT_PUBLISHERS,
+> = PubSubChannel::new();
+
+// Nobody follows repeats yet, room for a menu scrolling on them
+const BUTTON_REPEAT_CAPACITY: usize = 4;
+const BUTTON_REPEAT_SUBSCRIBERS: usize = 1;
+
+// Repeats of held buttons, apart so they never push presses and releases out of the events
+// bus. Dropped right away while nobody subscribes
+pub static BUTTON_REPEATS: PubSubChannel<
+    CriticalSectionRawMutex,
+    ButtonEvent,
+    BUTTON_REPEAT_CAPACITY,
+    BUTTON_REPEAT_SUBSCRIBERS,
+    BUTTON_EVENT_PUBLISHERS,
+> = PubSubChannel::new();
+
+// Debounce of each button in ms, set by its task
+static DEBOUNCE_MS: [AtomicU32; MAX_PLAYERS] = [const { AtomicU32::new(0) }; MAX_PLAYERS];
+
+// A press is only published once debounced, wait this long for the ones still settling
+pub fn settle_time() -> Duration {
+    let max_ms = DEBOUNCE_MS
+        .iter()
+        .map(|ms| ms.load(Ordering::Relaxed))
+        .max()
+        .unwrap_or(0);
+    Duration::from_millis(max_ms as u64)
+}
+
+// Skip events from before now, e.g. presses during an LED routine
+pub fn drain(events: &mut ButtonSubscriber) {
+    while events.try_next_message().is_some() {}
+}
+
+// What a button asked for in a menu
+#[derive(PartialEq, Eq, Format, Clone, Copy)]
+pub enum MenuPress {
+    // Pressed and released before a long press
+    Click(ButtonRole),
+    // Still held at the long press, its release is then no click
+    Hold(ButtonRole),
+}
+
+// Next click or long press, whatever else happened meanwhile
+pub async fn next_menu_press(events: &mut ButtonSubscriber, config: &ClickConfig) -> MenuPress {
+    loop {
+        match events.next_message_pure().await {
+            ButtonEvent::Released { player, held, .. } if held < config.long_press => {
+                return MenuPress::Click(player)
+            }
+            ButtonEvent::LongPress { player, .. } => return MenuPress::Hold(player),
+            _ => {}
+        }
+    }
+}
+
+// Published as it happens, by the button tasks and the bot
+pub fn publish(event: ButtonEvent) {
+    match event {
+        ButtonEvent::Repeat { .. } => BUTTON_REPEATS
+            .immediate_publisher()
+            .publish_immediate(event),
+        _ => BUTTON_EVENTS.immediate_publisher().publish_immediate(event),
+    }
+}
+
+// Sole owner of a button, classifies its debounced edges and publishes the events as they happen
+#[embassy_executor::task(pool_size = MAX_PLAYERS)]
+pub async fn watch_button(mut button: Button<'static>, config: ClickConfig) {
+    let role = button.role();
+    DEBOUNCE_MS[role.index()].store(button.debounce().as_millis() as u32, Ordering::Relaxed);
+    let mut decoder = ButtonDecoder::new(role, config);
+    loop {
+        let press = button.wait_for_press().await;
+        decoder
+            .on_press(press.as_micros())
+            .into_iter()
+            .for_each(publish);
+
+        // Keep the same release future across timer wakes, so no edge is lost mid-debounce
+        let mut release = pin!(button.wait_for_release());
+        let release = loop {
+            let deadline = decoder
+                .deadline()
+                .map(Instant::from_micros)
+                .unwrap_or(Instant::MAX);
+            match select(release.as_mut(), Timer::at(deadline)).await {
+                Either::First(at) => break at,
+                Either::Second(_) => {
+                    if let Some(event) = decoder.poll(Instant::now().as_micros()) {
+                        publish(event);
+                    }
+                }
+            }
+        };
+        if let Some(event) = decoder.on_release(release.as_micros()) {
+            publish(event);
+        }
+    }
+}
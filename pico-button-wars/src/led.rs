@@ -1,17 +1,16 @@
 use defmt::{debug, info, Format};
 use embassy_rp::gpio::{Level, Output, Pin};
-use embassy_time::{Duration, Instant, Timer};
+use embassy_time::{Duration, Timer};
 
-use crate::{
-    button::ButtonRole,
-    common::{LevelToStr, SimpleRngU64},
-};
+use crate::{button::ButtonRole, common::LevelToStr, config::config, watchdog::heartbeat};
+
+// Flashes of the offender LED on a false start
+pub const FALSE_START_STROBES: usize = 8;
 
 #[derive(PartialEq, Eq, Format, Clone, Copy)]
 pub enum LedRole {
     Onboard,
-    Player1,
-    Player2,
+    Player(ButtonRole),
 }
 
 // A simple abstraction over an output pin with a role
@@ -28,6 +27,10 @@ impl Led<'_> {
         }
     }
 
+    pub fn role(&self) -> LedRole {
+        self.role
+    }
+
     pub fn turn_on(&mut self) {
         self.output.set_high();
     }
@@ -64,10 +67,16 @@ impl Format for Led<'_> {
     }
 }
 
+// LED of a player, if the board has one
+pub fn player_led<'a, 'b>(leds: &'a mut [Led<'b>], player: ButtonRole) -> Option<&'a mut Led<'b>> {
+    leds.iter_mut()
+        .find(|led| led.role == LedRole::Player(player))
+}
+
 // ****** GameState Leds funcs ****** //
 
 // Non concurrent flashing pattern
-pub async fn waiting_state_leds(leds: &'_ mut [Led<'_>; 3]) {
+pub async fn waiting_state_leds(leds: &'_ mut [Led<'_>]) {
     // First pattern, fast flashes
     let mut duration = Duration::from_millis(200);
     for led in leds.iter_mut() {
@@ -80,9 +89,9 @@ pub async fn waiting_state_leds(leds: &'_ mut [Led<'_>; 3]) {
     let max_circles: usize = 10;
     duration = Duration::from_millis(100);
     loop {
-        leds[0].flash_pattern(duration, 1).await;
-        leds[1].flash_pattern(duration, 1).await;
-        leds[2].flash_pattern(duration, 1).await;
+        for led in leds.iter_mut() {
+            led.flash_pattern(duration, 1).await;
+        }
 
         // PERF: Need better pattern or algo
         i += 1;
@@ -101,7 +110,7 @@ pub async fn waiting_state_leds(leds: &'_ mut [Led<'_>; 3]) {
 }
 
 pub async fn highlight_round_winner(
-    leds: &'_ mut [Led<'_>; 3],
+    leds: &'_ mut [Led<'_>],
     winner_button: ButtonRole,
     current_score: usize,
 ) {
@@ -113,33 +122,25 @@ pub async fn highlight_round_winner(
     Timer::after_millis(500).await;
 
     // Flash the winner
-    let winner_led_role = match winner_button {
-        ButtonRole::Player1 => LedRole::Player1,
-        ButtonRole::Player2 => LedRole::Player2,
-    };
-
-    if let Some(winner_led) = leds.iter_mut().find(|led| led.role == winner_led_role) {
+    if let Some(winner_led) = player_led(leds, winner_button) {
         debug!(
             "Blinking winner {} for current_score: {}",
             winner_led, current_score
         );
+        let blink = config().leds.score_blink_ms as u64;
+        // A late round blinks for long, every point counts as progress
         for _ in 0..current_score {
+            heartbeat();
             winner_led.turn_on();
-            Timer::after_millis(500).await;
+            Timer::after_millis(blink).await;
 
             winner_led.turn_off();
-            Timer::after_millis(500).await;
+            Timer::after_millis(blink).await;
         }
     }
 }
 
-pub async fn highlight_game_winner(leds: &'_ mut [Led<'_>; 3], winner_button: ButtonRole) {
-    // Map button role to LED role
-    let winner_led_role = match winner_button {
-        ButtonRole::Player1 => LedRole::Player1,
-        ButtonRole::Player2 => LedRole::Player2,
-    };
-
+pub async fn highlight_game_winner(leds: &'_ mut [Led<'_>], winner_button: ButtonRole) {
     // buildup
     for speed in [150, 100, 80, 60, 40, 20].iter() {
         for led in leds.iter_mut() {
@@ -168,21 +169,23 @@ pub async fn highlight_game_winner(leds: &'_ mut [Led<'_>; 3], winner_button: Bu
     Timer::after_millis(500).await;
 
     // Victory pattern highlighting the winner LED
-    let winner_index = leds.iter().position(|led| led.role == winner_led_role);
+    let winner_index = leds
+        .iter()
+        .position(|led| led.role == LedRole::Player(winner_button));
 
     if let Some(index) = winner_index {
         for _ in 0..5 {
+            heartbeat();
             // Light up winner LED
             leds[index].turn_on();
 
             // Blink other LEDs to create spotlight effect
             for _ in 0..3 {
-                // HACK: AVOID DOUBLE MUTABLE BORROW
-                for i in 0..leds.len() {
+                for (i, led) in leds.iter_mut().enumerate() {
                     if i != index {
-                        leds[i].turn_on();
+                        led.turn_on();
                         Timer::after_millis(50).await;
-                        leds[i].turn_off();
+                        led.turn_off();
                     }
                 }
                 Timer::after_millis(150).await;
@@ -194,50 +197,57 @@ pub async fn highlight_game_winner(leds: &'_ mut [Led<'_>; 3], winner_button: Bu
         }
     }
 }
-// Turns on, then off for a random time with 'OFF' instant return for calculation of fastest player
-pub async fn round_playing_leds_routine_on_off(
-    leds: &'_ mut [Led<'_>; 3],
-    current_round: usize,
-) -> Instant {
-    // Signal that round 'i' is about to start then quick blinky
+pub fn all_leds_on(leds: &'_ mut [Led<'_>]) {
+    for led in leds.iter_mut() {
+        led.turn_on();
+    }
+}
+
+pub fn all_leds_off(leds: &'_ mut [Led<'_>]) {
+    for led in leds.iter_mut() {
+        led.turn_off();
+    }
+}
+
+// Signal that round 'i' is about to start, the hold phase follows right after
+pub async fn round_countdown_leds(leds: &'_ mut [Led<'_>], current_round: usize) {
     info!("Players get ready for round {}", current_round);
+    let countdown = config().leds.countdown_ms as u64;
+    // One blink per round played, the later the round the longer it goes
     for _ in 0..current_round + 1 {
-        for led in leds.iter_mut() {
-            led.turn_on();
-        }
-        Timer::after_millis(750).await;
-        for led in leds.iter_mut() {
-            led.turn_off();
-        }
-        Timer::after_millis(750).await;
+        heartbeat();
+        all_leds_on(leds);
+        Timer::after_millis(countdown).await;
+        all_leds_off(leds);
+        Timer::after_millis(countdown).await;
     }
     Timer::after_millis(500).await;
     for _ in 0..4 {
-        for led in leds.iter_mut() {
-            led.turn_on();
-        }
+        all_leds_on(leds);
         Timer::after_millis(150).await;
-        for led in leds.iter_mut() {
-            led.turn_off();
-        }
+        all_leds_off(leds);
         Timer::after_millis(150).await;
     }
+}
 
-    // Generate random time in ms between 2000-5000 ms for led signal to press button
-    let mut rng = SimpleRngU64::new();
-    let leds_duration = rng.generate_from_range(2000, 5000);
-    info!(
-        "Rng time for LED ON until shutoff for current game round: {} ms. ",
-        leds_duration
-    );
-    for led in leds.iter_mut() {
-        led.turn_on();
+// Strobe the offender LED alone so a false start can't be mistaken for GO
+pub async fn highlight_false_start(leds: &'_ mut [Led<'_>], offender: ButtonRole) {
+    all_leds_off(leds);
+    if let Some(offender_led) = player_led(leds, offender) {
+        debug!("Strobing false start for {}", offender_led);
+        let strobe = config().leds.false_start_strobe_ms as u64;
+        offender_led
+            .flash_pattern(Duration::from_millis(strobe), FALSE_START_STROBES)
+            .await;
     }
-    Timer::after_millis(leds_duration).await;
+}
 
-    for led in leds.iter_mut() {
-        led.turn_off();
+// Blink the onboard LED once per position of the selected match format in the presets
+pub async fn match_format_leds(leds: &'_ mut [Led<'_>], preset_index: usize) {
+    all_leds_off(leds);
+    if let Some(onboard_led) = leds.iter_mut().find(|led| led.role == LedRole::Onboard) {
+        onboard_led
+            .flash_pattern(Duration::from_millis(250), preset_index + 1)
+            .await;
     }
-    info!("GO!");
-    Instant::now()
 }
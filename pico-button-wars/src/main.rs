@@ -1,40 +1,119 @@
 #![no_std]
 #![no_main]
 
+mod board;
+mod bot;
 mod button;
+mod calibration;
 mod common;
+mod config;
+mod console;
+mod edge_capture;
+mod events;
 mod game;
+mod input;
 mod led;
-
+mod practice;
+mod rng;
+mod round;
+mod storage;
+mod watchdog;
+
+use core::array;
+use core::future::pending;
 use defmt::*;
+
 use embassy_executor::Spawner;
-use embassy_futures::select::{select, select3, Either, Either3};
+use embassy_futures::select::{select3, Either3};
 use embassy_rp::watchdog::*;
-use embassy_sync::{blocking_mutex::raw::ThreadModeRawMutex, mutex::Mutex};
-use embassy_time::{Duration, Ticker, Timer};
-use heapless::{Entry, FnvIndexMap};
+use embassy_sync::mutex::Mutex;
+use embassy_time::{Duration, Timer};
 
 use {defmt_rtt as _, panic_probe as _};
 
-use button::{monitor_double_longpress, Button, ButtonMutex, ButtonRole};
-use game::{
-    get_current_game_state_or_reset, transition_game_state, update_current_game_state_duration,
-    GameState,
+use board::{pin, seats, ONBOARD_LED_PIN, PLAYERS};
+use bot::play_bot;
+use button::{recognize_gestures, Button, ButtonRole, GESTURES};
+use button_wars_engine::{
+    as_millis, Bot, ClickConfig, FalseStartRule, GameEvent, Match, PracticeSession, RoundRules,
+    ScoringMetric, TiePolicy,
 };
+use calibration::{boot_combo_held, calibrate, debouncer, load_calibration, store_calibration};
+use config::load_config;
+use console::{set_last_stats, set_records, start_console, MenuCommand, MENU_COMMANDS};
+use edge_capture::{captured_edges, forward_captured_edges, start_edge_capture, CAPTURE_SLOTS};
+use events::{log_events, publish, EVENTS};
+use game::{Game, GameState, FORFEIT, SOFT_RESET};
+use input::{drain, next_menu_press, watch_button, MenuPress, BUTTON_EVENTS};
 use led::{
-    highlight_game_winner, highlight_round_winner, round_playing_leds_routine_on_off,
+    all_leds_off, highlight_game_winner, highlight_round_winner, match_format_leds,
     waiting_state_leds, Led, LedRole,
 };
+use practice::play_practice;
+use rng::{seed_rng, Rng};
+use round::{play_round, ROUND_TIMEOUT};
+use storage::{load_records, mount_storage, store_records, STORE};
+use watchdog::{feed_watchdog, heartbeat, WatchdogMutex};
+
+// Seed logged by a previous session to play its random draws again, None seeds from hardware
+const REPLAY_SEED: Option<u64> = None;
+
+// Trials in a solo practice session
+const PRACTICE_TRIALS: usize = 10;
 
-// Static watchdog & buttons periphs to allow for tasks
-static WATCHDOG: Mutex<ThreadModeRawMutex, Option<Watchdog>> = Mutex::new(None);
-static BUTTON_P1: ButtonMutex = Mutex::new(None);
-static BUTTON_P2: ButtonMutex = Mutex::new(None);
+// Static watchdog periph to allow for tasks
+static WATCHDOG: WatchdogMutex = Mutex::new(None);
 
 #[embassy_executor::main]
 async fn main(spawner: Spawner) {
     info!("Raspberry Pi Pico init in main executor...");
     let p = embassy_rp::init(Default::default());
+    seed_rng(REPLAY_SEED);
+
+    // Everything saved across resets, the config first since it says which pins to build
+    let mut store = mount_storage(p.FLASH);
+    let config = load_config(&mut store);
+    info!("Loaded {}", config);
+
+    // Initializing LED peripherals with output level as Low, the onboard one then one per player
+    let mut leds: [Led; PLAYERS + 1] = array::from_fn(|i| match i {
+        0 => Led::new(pin(ONBOARD_LED_PIN), LedRole::Onboard),
+        i => Led::new(
+            pin(config.pins[i - 1].led),
+            LedRole::Player(ButtonRole::ALL[i - 1]),
+        ),
+    });
+    for led in &leds {
+        info!("Initializing {}...", led);
+    }
+
+    // Subscribe before the button tasks start so no event is missed
+    let mut buttons = unwrap!(BUTTON_EVENTS.subscriber());
+
+    // Initializing Buttons peripherals with Pull UP and their calibrated debouncer, each one is owned by its own task
+    let calibration = load_calibration(&mut store);
+    info!("Loaded debounce calibration {}", calibration);
+    let mut records = load_records(&mut store);
+    info!("Loaded {}", records);
+    set_records(&records);
+    let mut player_buttons: [Button; PLAYERS] = array::from_fn(|i| {
+        let role = ButtonRole::ALL[i];
+        Button::new(
+            pin(config.pins[i].button),
+            role,
+            debouncer(calibration, role),
+        )
+    });
+
+    // Holding the first two buttons at boot measures them all again, before the watchdog is armed
+    if boot_combo_held(&player_buttons) {
+        let calibration = calibrate(&mut player_buttons, &mut leds).await;
+        store_calibration(&mut store, &calibration);
+        for button in player_buttons.iter_mut() {
+            button.set_debouncer(debouncer(Some(calibration), button.role()));
+        }
+    }
+
     // Watchdog for reset
     {
         let mut watchdog_unlocked = WATCHDOG.lock().await;
@@ -47,323 +126,355 @@ async fn main(spawner: Spawner) {
         }
 
         if let Some(wd) = watchdog_unlocked.as_mut() {
-            let wd_starve_time = Duration::from_secs(3);
+            let wd_starve_time = Duration::from_millis(config.watchdog_starve_ms as u64);
             wd.start(wd_starve_time);
             info!(
-                "Started watchdog on feed scheduale of {} s",
-                wd_starve_time.as_secs()
+                "Started watchdog on feed scheduale of {} ms",
+                wd_starve_time.as_millis()
             );
         }
     }
 
-    // Start watchdog feeding task so we can reset the game with longpress
+    // Start watchdog feeding task, it reboots the chip only if the main loop hangs
     spawner
-        .spawn(feed_watchdog(&WATCHDOG, Duration::from_millis(500)))
+        .spawn(feed_watchdog(
+            &WATCHDOG,
+            Duration::from_millis(config.watchdog_feed_ms as u64),
+        ))
         .unwrap();
 
-    // Initializing LED peripherals with output level as Low
-    let mut leds = [
-        Led::new(p.PIN_25, LedRole::Onboard),
-        Led::new(p.PIN_5, LedRole::Player1),
-        Led::new(p.PIN_8, LedRole::Player2),
-    ];
-    for led in &leds {
-        info!("Initializing {}...", led);
+    // Timestamp the button edges in PIO, the executor latency stays out of the reaction times.
+    // Past one state machine per player every button keeps the GPIO interrupt, so all players are timed alike
+    if PLAYERS <= CAPTURE_SLOTS {
+        let pins: [u8; PLAYERS] = array::from_fn(|i| config.pins[i].button);
+        let (capture_machines, capture_decoder) = start_edge_capture(p.PIO0, &pins);
+        spawner
+            .spawn(forward_captured_edges(capture_machines, capture_decoder))
+            .unwrap();
+        for button in player_buttons.iter_mut() {
+            button.use_captured_edges(captured_edges(button.role()));
+        }
+    } else {
+        warn!(
+            "{} players but {} capture state machines, timing the buttons from GPIO interrupts",
+            PLAYERS, CAPTURE_SLOTS
+        );
     }
 
-    // Initializing Buttons peripherals with Pull UP
-    {
-        let mut button_p1_unlocked = BUTTON_P1.lock().await;
-        *button_p1_unlocked = Some(Button::new(p.PIN_10, ButtonRole::Player1));
+    let click_config = ClickConfig::default();
+    for button in player_buttons {
+        info!("Initialized {}...", &button);
+        spawner.spawn(watch_button(button, click_config)).unwrap();
+    }
 
-        let mut button_p2_unlocked = BUTTON_P2.lock().await;
-        *button_p2_unlocked = Some(Button::new(p.PIN_11, ButtonRole::Player2));
+    // Game state in waiting mode, owned by the main loop
+    let mut game = Game::new();
+    game.update_state_duration();
 
-        // Making sure we panic if unproperly init
-        match *button_p1_unlocked {
-            None => crate::panic!("Could not initialize player 1 button."),
-            Some(_) => info!("Initialized 'BUTTON_P1'  as static shareable thread-safe ref",),
-        }
-        match *button_p2_unlocked {
-            None => crate::panic!("Could not initialize player 2 button."),
-            Some(_) => info!("Initialized 'BUTTON_P2'  as static shareable thread-safe ref",),
-        }
-    }
+    // Spawn the gesture task, holding both buttons soft resets the game
+    spawner
+        .spawn(recognize_gestures(
+            unwrap!(BUTTON_EVENTS.subscriber()),
+            &GESTURES,
+        ))
+        .unwrap();
 
-    // Initialize game state singleton in waiting mode
-    game::initialize_game().await;
-    info!("OK for Game Singleton.");
-    update_current_game_state_duration().await;
+    let round_rules = RoundRules {
+        scoring: ScoringMetric::PressEdge,
+        false_start: FalseStartRule::LoseRound,
+        tie_threshold: 1_000,
+        tie_policy: TiePolicy::Replay,
+        timeout: ROUND_TIMEOUT,
+        // Sudden death and total time hold with their own
+        foreperiod: config.foreperiod,
+        min_reaction: 100_000,
+    };
+    let mut game_match = Match::new(config.format, round_rules);
+    game_match.set_players(seats(&config));
+
+    // The bot takes the seat after the buttons
+    if let Some(reaction) = config.bot {
+        let bot = Bot::new(ButtonRole::ALL[PLAYERS], reaction);
+        spawner
+            .spawn(play_bot(unwrap!(EVENTS.subscriber()), bot))
+            .unwrap();
+    }
 
-    // Spawn the button press task to reset game on long press
+    // Log the game events from their own task
     spawner
-        .spawn(monitor_double_longpress(&BUTTON_P1, &BUTTON_P2, &WATCHDOG))
+        .spawn(log_events(
+            unwrap!(EVENTS.subscriber()),
+            round_rules.scoring,
+        ))
         .unwrap();
 
-    const TOTAL_ROUNDS: usize = 5;
-    const WIN_THRESHOLD: usize = TOTAL_ROUNDS.div_ceil(2);
-    let mut round_winner_times: [(Option<ButtonRole>, u64); TOTAL_ROUNDS] =
-        [(None::<ButtonRole>, u64::MIN); TOTAL_ROUNDS];
+    // The boot reads are done, the game loop and the console share the store from now on
+    *STORE.lock().await = Some(store);
+
+    // Serial console on the USB connector, following the game from the event bus
+    start_console(
+        &spawner,
+        p.USB,
+        unwrap!(EVENTS.subscriber()),
+        seats(&config),
+    );
 
-    let mut players_scores = FnvIndexMap::<ButtonRole, usize, 2>::new();
-    players_scores.insert(ButtonRole::Player1, 0).unwrap();
-    players_scores.insert(ButtonRole::Player2, 0).unwrap();
+    // Set from Waiting by the player who long pressed
+    let mut practice_player = ButtonRole::PLAYER_1;
 
     loop {
+        heartbeat();
         // Take the action based on game state
-        let current_state = get_current_game_state_or_reset(&WATCHDOG).await;
-
-        match current_state {
-            GameState::Waiting => {
-                info!("We are waiting! Resetting scores before next game");
-                // Resetting scores in case we are coming in from a previous game
-                for (role, time) in round_winner_times.iter_mut() {
-                    *role = None;
-                    *time = 0;
-                }
+        let current_state = game.state();
+
+        // Play the current state, unless the reset gesture aborts it midway
+        let step = async {
+            match current_state {
+                GameState::Waiting => {
+                    info!("We are waiting! Resetting scores before next game");
+                    // Resetting scores in case we are coming in from a previous game
+                    game_match.reset();
+                    // Presses from the last game or the reset gesture don't start a new one,
+                    // nor do console commands sent before the menu was back
+                    drain(&mut buttons);
+                    while MENU_COMMANDS.try_receive().is_ok() {}
+
+                    // Wait for a click from player 1, player 2 cycles the match format and
+                    // holding any button starts a practice session for its player. The console
+                    // can start the game or pick a format too
+                    let next_state = loop {
+                        heartbeat();
+                        waiting_state_leds(&mut leds).await;
 
-                if let Entry::Occupied(mut o) = players_scores.entry(ButtonRole::Player1) {
-                    *o.get_mut() = 0;
-                }
-                if let Entry::Occupied(mut o) = players_scores.entry(ButtonRole::Player2) {
-                    *o.get_mut() = 0;
-                }
-                // Wait for single press on each
-                loop {
-                    waiting_state_leds(&mut leds).await;
-
-                    info!("Press any button within the next 2 seconds to start the game...");
-                    let mut b1_unlocked = BUTTON_P1.lock().await;
-                    let mut b2_unlocked = BUTTON_P2.lock().await;
-                    if let (Some(b1_ref), Some(b2_ref)) =
-                        (b1_unlocked.as_mut(), b2_unlocked.as_mut())
-                    {
-                        match select3(
-                            b1_ref.wait_for_full_press(),
-                            b2_ref.wait_for_full_press(),
+                        info!(
+                            "Click player 1 button within the next 2 seconds to start a {} game, player 2 button to change format, or hold your button to practice...",
+                            game_match.format()
+                        );
+                        let menu_press = next_menu_press(&mut buttons, &click_config);
+                        let format = match select3(
+                            menu_press,
+                            MENU_COMMANDS.receive(),
                             Timer::after_secs(2),
                         )
                         .await
                         {
-                            Either3::First(_) => {
-                                info!("Player 1 button pressed, we can start the game!");
-                                break;
+                            Either3::First(MenuPress::Click(ButtonRole::PLAYER_1)) => {
+                                info!("Player 1 button clicked, we can start the game!");
+                                break GameState::Playing;
                             }
-                            Either3::Second(_) => {
-                                info!("Player 2 button pressed, we can start the game!");
-                                break;
+                            Either3::Second(MenuCommand::Start) => {
+                                info!("Started from the console, we can start the game!");
+                                break GameState::Playing;
                             }
-                            Either3::Third(_) => {
-                                info!("Timeout! going through routine again.")
+                            Either3::First(MenuPress::Click(ButtonRole::PLAYER_2)) => {
+                                let format = game_match.format().next_preset();
+                                info!("Player 2 button clicked, switching to {}", format);
+                                format
                             }
-                        }
-                    }
-                }
-                transition_game_state(GameState::Playing).await;
-            }
-            GameState::Playing => {
-                info!("We are playing!");
-                'rounds: for (i, round) in round_winner_times.iter_mut().enumerate() {
-                    info!("Players get ready for round #{}", i);
-
-                    // Insure we have both button mutex
-                    let mut b1_unlocked = BUTTON_P1.lock().await;
-                    let mut b2_unlocked = BUTTON_P2.lock().await;
-                    if let (Some(b1_ref), Some(b2_ref)) =
-                        (b1_unlocked.as_mut(), b2_unlocked.as_mut())
-                    {
-                        // Randomized time w/ light ON then OFF + pick first to full press w/ time
-                        let target_time_press =
-                            round_playing_leds_routine_on_off(&mut leds, i).await;
-                        let winner_timepress = select(
-                            b1_ref.measure_full_press_release(),
-                            b2_ref.measure_full_press_release(),
-                        )
-                        .await;
-
-                        // Use the button to match the winner led and add it to scores container
-                        let winner = match winner_timepress {
-                            Either::First(p1_release) => {
-                                info!("B1 was faster!");
-                                let p1_score = (p1_release - target_time_press).as_millis();
-                                (b1_ref.role(), p1_score)
+                            Either3::Second(MenuCommand::Format(format)) => {
+                                info!("Switching to {} from the console", format);
+                                format
+                            }
+                            Either3::First(MenuPress::Hold(player)) => {
+                                info!("{} button held, practice time!", player);
+                                practice_player = player;
+                                break GameState::Practicing;
                             }
-                            Either::Second(p2_release) => {
-                                info!("B2 was faster!");
-                                let p2_score = (p2_release - target_time_press).as_millis();
-                                (b2_ref.role(), p2_score)
+                            // The other players have no say in the menu
+                            Either3::First(MenuPress::Click(_)) => continue,
+                            Either3::Third(_) => {
+                                info!("Timeout! going through routine again.");
+                                continue;
                             }
                         };
-                        // Update the player scores
-                        if let Entry::Occupied(mut o) = players_scores.entry(winner.0) {
-                            *o.get_mut() += 1;
+                        game_match.set_format(format);
+                        publish(GameEvent::FormatChanged { format });
+                        match_format_leds(&mut leds, format.preset_index().unwrap_or(0)).await;
+                    };
+                    unwrap!(game.transition(next_state));
+                }
+                GameState::Playing => {
+                    info!("We are playing!");
+                    while !game_match.is_over() {
+                        heartbeat();
+                        let i = game_match.current_round();
+                        info!("Players get ready for round #{}", i);
+
+                        let mut rng = Rng;
+                        let Some(mut round) = game_match.start_round(&mut rng) else {
+                            warn!("No round left to play without a winner, computing results.");
+                            break;
+                        };
+                        // Randomized hold w/ light ON then OFF + engine picks the round winner
+                        let result =
+                            play_round(&mut leds, &mut buttons, &mut round, &mut rng).await;
+                        let outcome = game_match.finish_round(result);
+                        publish(GameEvent::RoundWon(outcome));
+                        if let Some(winner) = outcome.match_winner {
+                            publish(GameEvent::MatchWon { winner });
                         }
 
-                        // Save score and highlight round winner
-                        highlight_round_winner(
-                            &mut leds,
-                            winner.0,
-                            *players_scores.get(&winner.0).unwrap(),
-                        )
-                        .await;
-                        *round = (Some(winner.0), winner.1);
+                        if let Some(winner) = result.winner {
+                            // Highlight round winner with its updated score
+                            highlight_round_winner(&mut leds, winner, outcome.winner_score).await;
+                        }
+                        info!("Current scores: ");
+                        for (player, score) in game_match.scores() {
+                            info!(
+                                "{}: {} (total time {} ms)",
+                                player,
+                                score,
+                                as_millis(game_match.total_time(player))
+                            );
+                        }
+                        // Once the format decided the match, go on to ComputingResults
+                        if game_match.is_over() {
+                            break;
+                        }
+                        Timer::after_secs(2).await; // Just before starting next round
+                    }
+                    unwrap!(game.transition(GameState::ComputingResults));
+                }
+
+                GameState::ComputingResults => {
+                    info!("Computing results for current game...");
+                    let stats = game_match.stats();
+                    info!("{} rounds played", stats.rounds_played);
+                    for player in &stats.players {
                         info!(
-                            "DINGINGINGING! Congratulations for {} with a response time of {} ms",
-                            winner.0, winner.1
+                            "{}: {} wins, {} false starts, {} anticipations, {} missed GO",
+                            player.player,
+                            player.wins,
+                            player.false_starts,
+                            player.anticipations,
+                            player.no_presses
                         );
-                        // If we have a winner (best of 5), transition to Computing Results
-                        info!("Current scores: ");
-                        for (player, score) in &players_scores {
-                            info!("{}: {}", player, score);
-                            if *score == WIN_THRESHOLD {
-                                transition_game_state(GameState::ComputingResults).await;
-                                break 'rounds;
-                            }
+                        if let Some(reaction) = player.reaction {
+                            info!(
+                                "{} reaction over {} rounds: mean {} ms, median {} ms, best {} ms, worst {} ms, std dev {} ms, consistency {}/1000",
+                                player.player,
+                                reaction.count,
+                                as_millis(reaction.mean),
+                                as_millis(reaction.median),
+                                as_millis(reaction.min),
+                                as_millis(reaction.max),
+                                as_millis(reaction.std_dev),
+                                reaction.consistency
+                            );
+                        }
+                        if let Some(margin) = player.avg_margin {
+                            info!(
+                                "{} average margin of victory: {} ms",
+                                player.player,
+                                as_millis(margin)
+                            );
                         }
                     }
-                    info!(
-                        "Target window for ressetting game with long button double press of 2s..."
+                    // The bot seat after the buttons holds no records
+                    let new_record = records.record_match(
+                        game_match.format(),
+                        &stats,
+                        game_match.winner(),
+                        PLAYERS,
                     );
-                    drop(b1_unlocked);
-                    drop(b2_unlocked);
-                    Timer::after_secs(2).await; // Just before starting next round
+                    if new_record {
+                        info!("New record! Best reaction ever {}", records.best_reaction);
+                    }
+                    set_records(&records);
+                    if let Some(store) = STORE.lock().await.as_mut() {
+                        store_records(store, &records);
+                    }
+                    set_last_stats(&stats);
+                    match game_match.winner() {
+                        Some(winner) => {
+                            Timer::after_secs(1).await; // Let us read before transition!
+                            highlight_game_winner(&mut leds, winner).await;
+                        }
+                        None => warn!("No round was won, nothing to celebrate."),
+                    }
+                    unwrap!(game.transition(GameState::Finished));
                 }
-            }
-
-            GameState::ComputingResults => {
-                info!("Computing results for current game...");
-                let highest_scorer = players_scores
-                    .iter()
-                    .max_by_key(|&(_, score)| score)
-                    .map(|(player, _)| *player)
-                    .unwrap();
-
-                let mut best_response_time = u64::MAX;
-                let mut worst_response_time = u64::MIN;
-                let mut avg_response_time: u64 = 0;
-
-                for (role, time) in &round_winner_times {
-                    if let Some(r) = role {
-                        if *r == highest_scorer {
-                            // Compute stats for winner
-                            avg_response_time += *time;
-                            if *time < best_response_time {
-                                best_response_time = *time;
-                            }
-                            if *time > worst_response_time {
-                                worst_response_time = *time;
-                            }
+                GameState::Practicing => {
+                    let personal_best = records.personal_best(practice_player);
+                    info!(
+                        "{} practices alone over {} trials, personal best {} ms",
+                        practice_player,
+                        PRACTICE_TRIALS,
+                        personal_best.map(as_millis)
+                    );
+                    let mut session = PracticeSession::new(
+                        practice_player,
+                        PRACTICE_TRIALS,
+                        round_rules.timeout,
+                        round_rules.min_reaction,
+                    );
+                    play_practice(
+                        &mut leds,
+                        &mut buttons,
+                        &mut session,
+                        config.practice_foreperiod,
+                        personal_best,
+                    )
+                    .await;
+
+                    let summary = session.summary();
+                    let practice_best = records.practice_best();
+                    let new_best = records.record_practice(&summary);
+                    if new_best {
+                        if records.practice_best() != practice_best {
+                            info!(
+                                "New practice record! {} ms",
+                                records.practice_best().map(as_millis)
+                            );
                         }
+                        if let Some(store) = STORE.lock().await.as_mut() {
+                            store_records(store, &records);
+                        }
+                        set_records(&records);
+                    }
+                    publish(GameEvent::PracticeDone { summary, new_best });
+                    if new_best {
+                        highlight_game_winner(&mut leds, practice_player).await;
                     }
+                    Timer::after_secs(1).await;
+                    unwrap!(game.transition(GameState::Waiting));
+                }
+                GameState::Finished => {
+                    info!("Finished the game. Going back into waiting mode.");
+                    unwrap!(game.transition(GameState::Waiting));
                 }
-                avg_response_time /= WIN_THRESHOLD as u64;
-
-                // Log stats and celebrate winner
-                info!("Winner {} had an avg response time of {} ms (best time {} ms, worst time {} ms",
-                   highest_scorer,
-                    avg_response_time,
-                    best_response_time,
-                    worst_response_time
-                );
-                Timer::after_secs(1).await; // Let us read before transition!
-                highlight_game_winner(&mut leds, highest_scorer).await;
-                game::transition_game_state(GameState::Finished).await;
             }
-            GameState::Finished => {
-                info!("Finished the game. Going back into waiting mode.");
-                game::transition_game_state(GameState::Waiting).await;
+        };
+        // Giving up only makes sense during a match, mashing in the menu before it doesn't count
+        let forfeit = async {
+            match current_state {
+                GameState::Playing => {
+                    FORFEIT.reset();
+                    FORFEIT.wait().await
+                }
+                _ => pending().await,
             }
-        }
-    }
-}
-
-// TEST DEBOUNCE TIME. Uncomment to run as main
-// #[embassy_executor::main]
-async fn _test_debounce_time(spawner: Spawner) {
-    info!("Raspberry Pi Pico init in main executor...");
-    let p = embassy_rp::init(Default::default());
-
-    // Watchdog for reset
-    {
-        let mut watchdog_unlocked = WATCHDOG.lock().await;
-        *watchdog_unlocked = Some(Watchdog::new(p.WATCHDOG));
-
-        // Making sure we panic if unproperly init
-        match *watchdog_unlocked {
-            None => crate::panic!("Could not initialize watchdog timer"),
-            Some(_) => info!("Initialized 'WATCHDOG'  as static shareable thread-safe ref",),
-        }
-
-        if let Some(wd) = watchdog_unlocked.as_mut() {
-            let wd_starve_time = Duration::from_secs(3);
-            wd.start(wd_starve_time);
-            info!(
-                "Started watchdog on feed scheduale of {} s",
-                wd_starve_time.as_secs()
-            );
-        }
-    }
-
-    // Start watchdog feeding task so we can reset the game with longpress
-    spawner
-        .spawn(feed_watchdog(&WATCHDOG, Duration::from_millis(500)))
-        .unwrap();
-
-    // Initializing Buttons peripherals with Pull UP
-    let mut button_p1 = Button::new(p.PIN_10, ButtonRole::Player1);
-    let mut button_p2 = Button::new(p.PIN_11, ButtonRole::Player2);
-    info!("Initialized {}...", &button_p1);
-    info!("Initialized {}...", &button_p2);
-
-    // Initialize game state singleton in waiting mode
-    game::initialize_game().await;
-    transition_game_state(GameState::Playing).await;
-    loop {
-        // Take the action based on game state
-        let current_state = get_current_game_state_or_reset(&WATCHDOG).await;
-
-        // NOTE: Main priority compared to button reset + display refresh
-        match current_state {
-            GameState::Playing => {
-                let minimal_debounce_b1 = button_p1._measure_minimal_debounce(100, 10).await;
-                info!(
-                    "min debounce ms: {} for {}",
-                    minimal_debounce_b1, &button_p1
-                );
-
-                let minimal_debounce_b2 = button_p2._measure_minimal_debounce(100, 10).await;
-                info!(
-                    "min debounce ms: {} for {}",
-                    minimal_debounce_b2, &button_p2
-                );
-
-                game::transition_game_state(GameState::Finished).await;
+        };
+        match select3(step, SOFT_RESET.wait(), forfeit).await {
+            Either3::First(_) => {}
+            Either3::Second(_) => {
+                // Dropping the step future cancelled the round in flight
+                warn!("Soft reset from {}, back to waiting", current_state);
+                all_leds_off(&mut leds);
+                game_match.reset();
+                unwrap!(game.abort());
             }
-            GameState::Finished => {
-                info!("Done testing. ");
-                break;
+            Either3::Third(player) => {
+                // The round in flight is cancelled too, it's replayed if the match goes on
+                all_leds_off(&mut leds);
+                if let Some(winner) = game_match.forfeit(player) {
+                    info!("{} forfeited, {} wins the match", player, winner);
+                    publish(GameEvent::MatchWon { winner });
+                }
+                if game_match.is_over() {
+                    unwrap!(game.transition(GameState::ComputingResults));
+                }
             }
-
-            _ => error!("err"),
         }
     }
 }
-
-#[embassy_executor::task(pool_size = 1)]
-pub async fn feed_watchdog(
-    wd: &'static Mutex<ThreadModeRawMutex, Option<Watchdog>>,
-    feed_schedule: Duration,
-) {
-    let mut ticker = Ticker::every(feed_schedule);
-
-    loop {
-        {
-            let mut wd_unlocked = wd.lock().await;
-            if let Some(wd) = wd_unlocked.as_mut() {
-                wd.feed();
-                // info!("watchdog fed")
-            }
-        } // watchdog lock dropped here
-        ticker.next().await;
-    }
-}
@@ -0,0 +1,101 @@
+use embassy_futures::select::{select, Either};
+use embassy_time::{Duration, Instant, Timer};
+
+use button_wars_engine::{
+    ButtonEvent, ButtonRole, Foreperiod, GameEvent, Micros, PracticeSession, Trial,
+};
+
+use crate::{
+    events::publish,
+    input::{drain, ButtonSubscriber},
+    led::{all_leds_off, all_leds_on, highlight_false_start, player_led, Led, LedRole},
+    rng::Rng,
+    watchdog::heartbeat,
+};
+
+// Plays every trial of the session, only the practicing player's button counts
+pub async fn play_practice(
+    leds: &'_ mut [Led<'_>],
+    buttons: &mut ButtonSubscriber,
+    session: &mut PracticeSession,
+    foreperiod: Foreperiod,
+    personal_best: Option<Micros>,
+) {
+    let player = session.player();
+    while !session.is_over() {
+        heartbeat();
+        let index = session.current_trial();
+        all_leds_off(leds);
+        Timer::after_secs(1).await;
+
+        // Random hold like a round, LEDs ON until GO
+        let go_deadline = Instant::now() + Duration::from_micros(foreperiod.sample(&mut Rng));
+        drain(buttons);
+        all_leds_on(leds);
+        let trial = match next_player_press(buttons, player, go_deadline).await {
+            Some(at) => session.on_press(at),
+            None => {
+                all_leds_off(leds);
+                session.go(Instant::now().as_micros());
+                race(buttons, session).await
+            }
+        };
+        let Some(trial) = trial else {
+            continue;
+        };
+        heartbeat();
+        publish(GameEvent::TrialDone {
+            player,
+            index,
+            trial,
+            personal_best,
+        });
+        trial_feedback_leds(leds, player, trial).await;
+    }
+}
+
+// First press of `player` before `until`, if any
+async fn next_player_press(
+    buttons: &mut ButtonSubscriber,
+    player: ButtonRole,
+    until: Instant,
+) -> Option<Micros> {
+    loop {
+        match select(buttons.next_message_pure(), Timer::at(until)).await {
+            Either::First(ButtonEvent::Pressed { player: p, at }) if p == player => {
+                return Some(at)
+            }
+            Either::First(_) => {}
+            Either::Second(_) => return None,
+        }
+    }
+}
+
+async fn race(buttons: &mut ButtonSubscriber, session: &mut PracticeSession) -> Option<Trial> {
+    let deadline = session
+        .deadline()
+        .map(Instant::from_micros)
+        .unwrap_or(Instant::MAX);
+    match next_player_press(buttons, session.player(), deadline).await {
+        Some(at) => session.on_press(at),
+        None => session.poll(Instant::now().as_micros()),
+    }
+}
+
+// A reaction blinks the player LED once per started 100 ms, a miss blinks the onboard LED
+async fn trial_feedback_leds(leds: &'_ mut [Led<'_>], player: ButtonRole, trial: Trial) {
+    match trial {
+        Trial::Reacted(time) => {
+            if let Some(led) = player_led(leds, player) {
+                let blinks = time.div_ceil(100_000).min(10) as usize;
+                led.flash_pattern(Duration::from_millis(150), blinks).await;
+            }
+        }
+        Trial::FalseStart | Trial::Anticipated(_) => highlight_false_start(leds, player).await,
+        Trial::NoPress => {
+            if let Some(onboard) = leds.iter_mut().find(|led| led.role() == LedRole::Onboard) {
+                onboard.flash_pattern(Duration::from_millis(500), 1).await;
+            }
+        }
+    }
+}
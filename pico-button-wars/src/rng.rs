@@ -0,0 +1,32 @@
+use core::cell::RefCell;
+
+use defmt::info;
+use embassy_rp::clocks::RoscRng;
+use embassy_sync::blocking_mutex::{raw::CriticalSectionRawMutex, Mutex};
+use rand_core::RngCore;
+
+use button_wars_engine::{RandomSource, Xoshiro256};
+
+// The one generator of the firmware, reseeded at boot before any draw
+static RNG: Mutex<CriticalSectionRawMutex, RefCell<Xoshiro256>> =
+    Mutex::new(RefCell::new(Xoshiro256::from_seed(0)));
+
+// Seed from the ROSC random bit, unless replaying a logged seed
+pub fn seed_rng(replay_seed: Option<u64>) {
+    let seed = replay_seed.unwrap_or_else(|| RoscRng.next_u64());
+    RNG.lock(|rng| *rng.borrow_mut() = Xoshiro256::from_seed(seed));
+    info!(
+        "Seeded the RNG with {=u64:#x}, set it as the replay seed to play this session again",
+        seed
+    );
+}
+
+// Handle on the shared generator, any task can hold one
+#[derive(Clone, Copy)]
+pub struct Rng;
+
+impl RandomSource for Rng {
+    fn next_u64(&mut self) -> u64 {
+        RNG.lock(|rng| rng.borrow_mut().next_u64())
+    }
+}
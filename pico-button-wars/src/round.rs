@@ -0,0 +1,137 @@
+use defmt::{info, warn};
+use embassy_futures::select::{select, Either};
+use embassy_time::{Duration, Instant, Timer};
+
+use button_wars_engine::{
+    as_millis, ButtonEvent, GameEvent, Micros, Round, RoundEvent, RoundResult, MAX_PLAYERS,
+};
+
+use crate::{
+    events::publish,
+    input::{drain, settle_time, ButtonSubscriber},
+    led::{all_leds_off, all_leds_on, highlight_false_start, round_countdown_leds, Led},
+    rng::Rng,
+    watchdog::heartbeat,
+};
+
+// Time after GO given to every player to press and release
+pub const ROUND_TIMEOUT: Micros = 3_000_000;
+
+// Drives a round of the engine with the button events and LEDs, from countdown to decision
+pub async fn play_round(
+    leds: &'_ mut [Led<'_>],
+    buttons: &mut ButtonSubscriber,
+    round: &mut Round,
+    rng: &mut Rng,
+) -> RoundResult {
+    publish(GameEvent::RoundStarted {
+        index: round.index(),
+    });
+    round_countdown_leds(leds, round.index()).await;
+
+    // Random LED hold until the signal to press, drawn from the foreperiod of the rules
+    let leds_duration = round.rules().foreperiod.sample(rng);
+    info!(
+        "Rng time for LED ON until shutoff for current game round: {} ms. ",
+        as_millis(leds_duration)
+    );
+    let go_deadline = Instant::now() + Duration::from_micros(leds_duration);
+
+    // Presses during the countdown don't count
+    drain(buttons);
+    if let Some(result) = hold_phase(leds, buttons, round, go_deadline).await {
+        return result;
+    }
+    all_leds_off(leds);
+    let go_at = Instant::now().as_micros();
+    round.go(go_at);
+    publish(GameEvent::Go { at: go_at });
+
+    race_phase(buttons, round).await
+}
+
+// LEDs stay ON until GO, any press meanwhile is a false start for the engine to penalize
+async fn hold_phase(
+    leds: &'_ mut [Led<'_>],
+    buttons: &mut ButtonSubscriber,
+    round: &mut Round,
+    go_deadline: Instant,
+) -> Option<RoundResult> {
+    loop {
+        heartbeat();
+        all_leds_on(leds);
+        let (role, press) = match select(Timer::at(go_deadline), buttons.next_message_pure()).await
+        {
+            Either::First(_) => return None,
+            Either::Second(ButtonEvent::Pressed { player, at }) => (player, at),
+            Either::Second(_) => continue,
+        };
+        publish(GameEvent::ButtonPressed {
+            player: role,
+            at: press,
+        });
+
+        if let Some(event @ RoundEvent::FalseStart { player, .. }) = round.on_press(role, press) {
+            // The hold may have run long already, the strobe starts afresh
+            heartbeat();
+            warn!(
+                "{} ({} ms before GO)",
+                event,
+                as_millis(go_deadline.as_micros().saturating_sub(press))
+            );
+            highlight_false_start(leds, player).await;
+        }
+
+        if let Some(result) = round.result() {
+            all_leds_off(leds);
+            return Some(result);
+        }
+    }
+}
+
+// Each button task timestamps its own presses, so the engine can compare them fairly whatever
+// order they are read in, once nobody can beat or tie the fastest player anymore
+async fn race_phase(buttons: &mut ButtonSubscriber, round: &mut Round) -> RoundResult {
+    // Presses only get published once debounced, give the ones before the deadline time to land
+    let settle = settle_time();
+    // A button held since the hold phase must be released and pressed again to race
+    let mut pressed = [false; MAX_PLAYERS];
+    loop {
+        heartbeat();
+        let deadline = round
+            .deadline()
+            .map(|at| Instant::from_micros(at) + settle)
+            .unwrap_or(Instant::MAX);
+
+        match select(buttons.next_message_pure(), Timer::at(deadline)).await {
+            Either::First(ButtonEvent::Pressed { player, at }) => {
+                pressed[player.index()] = true;
+                publish(GameEvent::ButtonPressed { player, at });
+                match round.on_press(player, at) {
+                    Some(event @ RoundEvent::Anticipated { .. }) => {
+                        warn!("{}, too fast to have seen GO", event)
+                    }
+                    // Pressed during the hold, the press only made it out after GO
+                    Some(event @ RoundEvent::FalseStart { .. }) => {
+                        warn!("{}, pressed before GO", event)
+                    }
+                    _ => {}
+                }
+            }
+            Either::First(ButtonEvent::Released { player, at, .. }) => {
+                if pressed[player.index()] {
+                    round.on_release(player, at);
+                }
+            }
+            // Long presses and clicks mean nothing to a race
+            Either::First(_) => {}
+            Either::Second(_) => {
+                round.poll(Instant::now().as_micros());
+            }
+        }
+        // Keep recording the slower player after the decision, until release or timeout
+        if let (true, Some(result)) = (round.is_complete(), round.result()) {
+            return result;
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use defmt::{info, unwrap, warn};
+use embassy_rp::flash::{Blocking, Flash, ERASE_SIZE};
+use embassy_rp::peripherals::FLASH;
+use embassy_sync::{blocking_mutex::raw::ThreadModeRawMutex, mutex::Mutex};
+
+use button_wars_engine::{storage::MAX_VALUE, Key, Records, Store};
+
+pub const FLASH_SIZE: usize = 2 * 1024 * 1024;
+// Sectors of the STORAGE region of memory.x, the last ones of the flash
+const STORAGE_SECTORS: usize = 4;
+const STORAGE_OFFSET: u32 = (FLASH_SIZE - STORAGE_SECTORS * ERASE_SIZE) as u32;
+
+pub type BoardFlash = Flash<'static, FLASH, Blocking, FLASH_SIZE>;
+pub type BoardStore = Store<BoardFlash>;
+
+// Handed over by main once the boot reads are done, shared by the game loop and the console
+pub static STORE: Mutex<ThreadModeRawMutex, Option<BoardStore>> = Mutex::new(None);
+
+// Everything kept across resets lives in this store, formatted on a fresh board
+pub fn mount_storage(flash: FLASH) -> BoardStore {
+    let flash = BoardFlash::new_blocking(flash);
+    unwrap!(Store::mount(flash, STORAGE_OFFSET, STORAGE_SECTORS as u32))
+}
+
+// Latest value of `key` as parsed by `parse`, none if never saved or unreadable
+pub fn load<T>(
+    store: &mut BoardStore,
+    key: Key,
+    parse: impl FnOnce(&[u8]) -> Option<T>,
+) -> Option<T> {
+    let mut bytes = [0; MAX_VALUE];
+    match store.read(key, &mut bytes) {
+        Ok(len) => parse(&bytes[..len?]),
+        Err(e) => {
+            warn!("Could not read {}: {}", key, e);
+            None
+        }
+    }
+}
+
+// A failed save only loses this value, the previous one stays, true when saved
+pub fn save(store: &mut BoardStore, key: Key, bytes: &[u8]) -> bool {
+    match store.write(key, bytes) {
+        Ok(()) => {
+            info!("Saved {}", key);
+            true
+        }
+        Err(e) => {
+            warn!("Could not save {}: {}", key, e);
+            false
+        }
+    }
+}
+
+pub fn load_records(store: &mut BoardStore) -> Records {
+    load(store, Key::Records, Records::from_bytes).unwrap_or_default()
+}
+
+pub fn store_records(store: &mut BoardStore, records: &Records) {
+    save(store, Key::Records, &records.to_bytes());
+}
@@ -0,0 +1,90 @@
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use defmt::{error, Format};
+use embassy_rp::watchdog::Watchdog;
+use embassy_sync::{blocking_mutex::raw::ThreadModeRawMutex, mutex::Mutex};
+use embassy_time::{Duration, Instant, Ticker};
+
+use button_wars_engine::Config;
+
+use crate::{led::FALSE_START_STROBES, round::ROUND_TIMEOUT};
+
+pub type WatchdogMutex = Mutex<ThreadModeRawMutex, Option<Watchdog>>;
+
+// Past this long without a heartbeat the main loop is taken for hung
+const LIVENESS_TIMEOUT_MS: u64 = 60_000;
+const LIVENESS_TIMEOUT: Duration = Duration::from_millis(LIVENESS_TIMEOUT_MS);
+
+// Checked at build time against the longest a round goes between two heartbeats, with every
+// config timing at its cap: the hold before GO, a false start strobe, or the race until its
+// timeout with the last presses settling. The countdown and score routines beat on every
+// blink, late rounds add nothing. A healthy match never comes close to the timeout
+const _: () = {
+    let hold = Config::MAX_HOLD / 1_000;
+    let strobe = 2 * FALSE_START_STROBES as u64 * Config::MAX_TIMING_MS as u64;
+    let race = ROUND_TIMEOUT / 1_000 + Config::MAX_TIMING_MS as u64;
+    let mut longest_step = hold;
+    if strobe > longest_step {
+        longest_step = strobe;
+    }
+    if race > longest_step {
+        longest_step = race;
+    }
+    assert!(3 * longest_step <= LIVENESS_TIMEOUT_MS);
+};
+
+// Milliseconds from boot of the last heartbeat of the main loop, wrapping every 49.7 days
+static LAST_HEARTBEAT: AtomicU32 = AtomicU32::new(0);
+
+// Tell the feeder the main loop is still making progress
+pub fn heartbeat() {
+    LAST_HEARTBEAT.store(now_ms(), Ordering::Relaxed);
+}
+
+// Low 32 bits of the uptime, wrapping like the heartbeat
+fn now_ms() -> u32 {
+    Instant::now().as_millis() as u32
+}
+
+#[derive(Format)]
+struct Stalled {
+    since_ms: u64,
+}
+
+fn check_liveness() -> Result<(), Stalled> {
+    // Right across the wrap too, the gap itself is far shorter than 49.7 days
+    let since_ms = now_ms().wrapping_sub(LAST_HEARTBEAT.load(Ordering::Relaxed));
+    let since = Duration::from_millis(since_ms as u64);
+    match since > LIVENESS_TIMEOUT {
+        true => Err(Stalled {
+            since_ms: since.as_millis(),
+        }),
+        false => Ok(()),
+    }
+}
+
+// Feed while the main loop beats, a hung game stops the feeding and the chip reboots.
+// Last resort only, the reset gesture goes through the soft reset instead
+#[embassy_executor::task(pool_size = 1)]
+pub async fn feed_watchdog(wd: &'static WatchdogMutex, feed_schedule: Duration) {
+    let mut ticker = Ticker::every(feed_schedule);
+    heartbeat();
+
+    loop {
+        if let Err(stalled) = check_liveness() {
+            error!(
+                "Main loop stalled ({}), letting the watchdog reboot the chip",
+                stalled
+            );
+            return;
+        }
+        {
+            let mut wd_unlocked = wd.lock().await;
+            if let Some(wd) = wd_unlocked.as_mut() {
+                wd.feed();
+                // info!("watchdog fed")
+            }
+        } // watchdog lock dropped here
+        ticker.next().await;
+    }
+}